@@ -1,12 +1,11 @@
-use crate::assembler::Assembler;
-use crate::cpu::CPU;
+use riscvemulator::{AssembleError, Assembler, CpuConfig, Disassembler, Elf, Format, SyscallAbi, WatchKind, Watchpoint, CPU};
+use riscvemulator::register::abi_name;
 use macroquad::prelude::*;
 use macroquad::ui;
 use macroquad::ui::{root_ui, Ui};
 use macroquad::ui::widgets::Group;
 use std::fs;
 use ui::{hash, widgets};
-use crate::cpu;
 
 // its like a state but its an action 
 enum CurrentAction {
@@ -16,26 +15,64 @@ enum CurrentAction {
     Wait,
 }
 
+// how many bytes of memory get shown at once
+const MEMORY_PAGE: u32 = 0x200;
+// lines of uart output kept around, older ones scroll off the top
+const CONSOLE_LINES: usize = 200;
+// where programs can open files with the linux syscalls
+const SANDBOX: &str = "./sandbox";
+// how many instructions run each frame while its running, faster and slower double and halve it
+const DEFAULT_SPEED: u64 = 100;
+const MAX_SPEED: u64 = 1 << 20;
+
 // i mean why not just put the assembler and cpu here #easy
 pub struct AppState {
     cpu: CPU,
     assembler: Option<Assembler>,
+    // set instead of the assembler when the program is an elf executable
+    elf: Option<Elf>,
     cur_state: CurrentAction,
+    // whatever went wrong assembling the selected program
+    errors: Vec<AssembleError>,
+    // also knows whether to show registers as sp, a0... instead of x2, x10...
+    disassembler: Disassembler,
+    // which of CpuConfig::presets the cpu was made with, and what ecall does on top of it
+    preset: usize,
+    syscalls: SyscallAbi,
+    // the first address shown in the memory view
+    memory_view: u32,
+    // everything the program has sent on the uart
+    console: String,
+    // running on its own instead of a step at a time, speed instructions each frame
+    running: bool,
+    speed: u64,
+    // show the source around the pc instead of the disassembly
+    show_source: bool,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         AppState {
-            cpu: CPU::default(),
+            cpu: new_cpu(CpuConfig::default()).unwrap(),
             assembler: None,
+            elf: None,
             cur_state: CurrentAction::Wait,
+            errors: vec![],
+            disassembler: Disassembler::default(),
+            preset: 0,
+            syscalls: SyscallAbi::default(),
+            memory_view: 0,
+            console: String::new(),
+            running: false,
+            speed: DEFAULT_SPEED,
+            show_source: false,
         }
     }
 }
 
 fn get_file_names() -> Vec<String> {
     let dir = fs::read_dir("./programs").unwrap();
-    // strip file ending to display the name
+    // strip file ending to display the name, elf files keep whatever name they have
     dir.map(|x| {
         x.unwrap()
             .file_name()
@@ -56,7 +93,7 @@ pub fn update_app(state: &mut AppState) {
 }
 
 // i hate making ui i bet this looks so bad on other screens
-fn describe_program(ui: &mut Ui, program: &Vec<String>) {
+fn describe_program(ui: &mut Ui, program: &[String]) {
     Group::new(hash!(), vec2(screen_width()/2., screen_height()))
         .position(vec2(10., 50.))
         .ui(ui, |ui| {
@@ -76,7 +113,7 @@ fn draw_program_view(state: &mut AppState) {
                 state.cur_state = CurrentAction::Wait;
             }
 
-            describe_program(ui, &program)
+            describe_program(ui, program)
         });
 }
 
@@ -93,72 +130,291 @@ fn draw_cpu_view(state: &mut AppState) {
             Group::new(hash!(), vec2(screen_width() - 20.0, screen_height() - 20.0))
                 .position(vec2(10., 10.))
                 .ui(ui, |ui| {
+                    // runs until it stops by itself, hits a breakpoint or watchpoint or gets paused
+                    if state.running {
+                        state.cpu.run(state.speed);
+                        if state.cpu.is_halted() || state.cpu.at_breakpoint() || state.cpu.view_watch_hit().is_some() {
+                            state.running = false;
+                        }
+                    }
+
                     // need to show what the cpu is up to
                     ui.label(None, &format!("Program Counter: {}", state.cpu.get_pc())); 
-                    // also control the program flow (need automatic way later)
+                    // also control the program flow
                     if ui.button(None, "Step Program") {
                         state.cpu.step();
                     }
+                    if ui.button(vec2(250., 30.), if state.running { "Pause" } else { "Run" }) {
+                        state.running = !state.running && !state.cpu.is_halted();
+                    }
+                    if ui.button(vec2(300., 30.), "Slower") {
+                        state.speed = (state.speed / 2).max(1);
+                    }
+                    if ui.button(vec2(350., 30.), "Faster") {
+                        state.speed = (state.speed * 2).min(MAX_SPEED);
+                    }
+                    ui.label(vec2(400., 30.), &format!("{}/frame", state.speed));
+                    if ui.button(vec2(500., 30.), if state.show_source { "Disassembly" } else { "Source" }) {
+                        state.show_source = !state.show_source;
+                    }
+                    // undo the last step, or keep undoing back to a breakpoint. what got printed
+                    // to the console stays there though
+                    if ui.button(vec2(600., 30.), "Step Back") {
+                        state.running = false;
+                        state.cpu.step_back();
+                    }
+                    if ui.button(vec2(670., 30.), "Reverse Continue") {
+                        state.running = false;
+                        state.cpu.reverse_continue(u64::MAX);
+                    }
 
                     // reset (now doesnt reset all of memory (which has the program))
+                    // so load it again to put back any data the program changed
                     if ui.button(vec2(250., 10.), "Reset") {
                         state.cpu.reset();
+                        state.running = false;
+                        state.console.clear();
+                        reload_program(state);
                     }
                     // its like reset but also escapes the program to load another
                     if ui.button(vec2(300., 10.), "Back") {
                         state.cur_state = CurrentAction::Wait;
                         state.cpu.reset();
+                        state.running = false;
+                        state.console.clear();
+                    }
+
+                    // swap between x0..x31 and the abi names
+                    let names = if state.disassembler.abi_names { "xN Names" } else { "ABI Names" };
+                    if ui.button(vec2(350., 10.), names) {
+                        state.disassembler.abi_names = !state.disassembler.abi_names;
                     }
 
-                    describe_mem_reg(ui, &state.cpu);
+                    // page through memory, it can be way too big to show all of it
+                    let config = *state.cpu.view_config();
+                    if ui.button(vec2(450., 10.), "Mem <") {
+                        state.memory_view = state.memory_view.saturating_sub(MEMORY_PAGE).max(config.ram_base);
+                    }
+                    if ui.button(vec2(500., 10.), "Mem >") && (state.memory_view as u64) + (MEMORY_PAGE as u64) < config.ram_end() {
+                        state.memory_view += MEMORY_PAGE;
+                    }
+
+                    if let Some(address) = describe_mem_reg(ui, &state.cpu, state.disassembler.abi_names, state.memory_view) {
+                        cycle_watchpoint(&mut state.cpu, address);
+                    }
                 });
             describe_cpu(ui, &state.cpu);
+            describe_csrs(ui, &state.cpu);
+            // clicking a line in either one puts a breakpoint on it, or takes it off
+            let clicked = match &state.assembler {
+                Some(assembler) if state.show_source => describe_source(ui, &state.cpu, assembler),
+                _ => describe_disassembly(ui, &state.cpu, &state.disassembler),
+            };
+            if let Some(address) = clicked {
+                if state.cpu.view_breakpoints().contains(&address) {
+                    state.cpu.remove_breakpoint(address);
+                } else {
+                    state.cpu.set_breakpoint(address);
+                }
+            }
+            update_console(state);
+            describe_console(ui, &state.console);
+        });
+}
+
+// typing goes to the uart and whatever the program sent comes back out
+fn update_console(state: &mut AppState) {
+    let Some(uart) = state.cpu.view_uart() else {
+        return;
+    };
+    while let Some(c) = get_char_pressed() {
+        // enter comes through as \r but programs look for \n
+        let c = if c == '\r' { '\n' } else { c };
+        uart.receive(c.to_string().as_bytes());
+    }
+    let output = uart.take_output();
+    state.console.push_str(&String::from_utf8_lossy(&output));
+
+    let lines = state.console.lines().count();
+    if lines > CONSOLE_LINES {
+        let cut = state.console.match_indices('\n').nth(lines - CONSOLE_LINES - 1).map_or(0, |(i, _)| i + 1);
+        state.console.drain(..cut);
+    }
+}
+
+// the newest lines of uart output, as many as fit
+fn describe_console(ui: &mut Ui, console: &str) {
+    let height = (screen_height() - 570.).max(45.);
+    let fit = (height / 15.) as usize;
+    Group::new(hash!(), vec2(screen_width()/2. - 40., height))
+        .position(vec2(screen_width()/2. + 20., 560.))
+        .ui(ui, |ui| {
+            ui.label(None, "Console:");
+            let lines = console.split('\n').collect::<Vec<&str>>();
+            for line in &lines[lines.len().saturating_sub(fit - 1)..] {
+                ui.label(None, line);
+            }
         });
 }
 
 
 // show what instruction the cpu has loaded and current values of it
-fn describe_cpu(ui: &mut Ui,cpu: &cpu::CPU)  {
-    let info = cpu.view_instr_info();
-    if info.name.is_none() {
+fn describe_cpu(ui: &mut Ui,cpu: &CPU)  {
+    let Some(instruction) = cpu.view_instruction() else {
         return;
-    }
+    };
+    let fields = instruction.fields();
     Group::new(hash!(), vec2(screen_width()/2., 100.))
         .position(vec2(screen_width()/2. + 20., 50.))
         .ui(ui, |ui| {
-            ui.label(None, &format!("Instruction: {}", info.name.clone().unwrap()));
-            if info.rd.is_some() {
-                ui.label(None, &format!("RD: {}", info.rd.unwrap()));
+            ui.label(None, &format!("Instruction: {}", instruction.name()));
+            // only the operands this format actually has
+            let (rd, rs1, rs2, imm) = match instruction.format() {
+                Format::R => (true, true, true, false),
+                Format::Store | Format::Branch => (false, true, true, true),
+                Format::Upper | Format::Jump => (true, false, false, true),
+                Format::System => (false, false, false, false),
+                _ => (true, true, false, true),
+            };
+            if rd {
+                ui.label(None, &format!("RD: {}", fields.rd));
+            }
+            if rs1 {
+                ui.label(None, &format!("R1: {}", fields.rs1));
             }
-            ui.label(None, &format!("R1: {}", info.rs1));
-            if info.rs2.is_some() {
-                ui.label(None, &format!("R2: {}", info.rs2.unwrap()));
+            if rs2 {
+                ui.label(None, &format!("R2: {}", fields.rs2));
             }
-            if info.imm.is_some() {
-                ui.label(None, &format!("IMM: {}", info.imm.unwrap()));
+            if imm {
+                ui.label(None, &format!("IMM: {}", fields.imm));
             }
         });
 }
 
-// show the memory and register contents of the cpu at each step
-fn describe_mem_reg(ui: &mut Ui,cpu: &cpu::CPU)  {
+// show the main csrs and whatever stopped the program if it crashed
+fn describe_csrs(ui: &mut Ui, cpu: &CPU) {
+    let csrs = cpu.view_csrs();
+    Group::new(hash!(), vec2(screen_width()/2., 180.))
+        .position(vec2(screen_width()/2. + 20., 160.))
+        .ui(ui, |ui| {
+            if let Some(fault) = cpu.view_fault() {
+                ui.label(None, &format!("Fault: {}", fault));
+            }
+            if let Some(hit) = cpu.view_watch_hit() {
+                ui.label(None, &format!("Watch: {}", hit));
+            }
+            ui.label(None, &format!("mtvec: 0x{:x}", csrs.mtvec));
+            ui.label(None, &format!("mepc: 0x{:x}", csrs.mepc));
+            ui.label(None, &format!("mcause: {}", csrs.mcause));
+            ui.label(None, &format!("mtval: 0x{:x}", csrs.mtval));
+            ui.label(None, &format!("mstatus: 0x{:x}", csrs.mstatus));
+            ui.label(None, &format!("cycle: {}", csrs.cycle));
+            ui.label(None, &format!("instret: {}", csrs.instret));
+        });
+}
+
+// > is the next one to run and * has a breakpoint
+fn marker(current: bool, breakpoint: bool) -> &'static str {
+    match (current, breakpoint) {
+        (true, true) => ">*",
+        (true, false) => "> ",
+        (false, true) => " *",
+        (false, false) => "  ",
+    }
+}
+
+// the instructions around the pc as assembly, gives back the address of one if it was clicked
+fn describe_disassembly(ui: &mut Ui, cpu: &CPU, disassembler: &Disassembler) -> Option<u32> {
+    let pc = cpu.get_pc();
+    let mut clicked = None;
+    Group::new(hash!(), vec2(screen_width()/2., 200.))
+        .position(vec2(screen_width()/2. + 20., 350.))
+        .ui(ui, |ui| {
+            for i in -4..8 {
+                let address = pc.wrapping_add((i * 4) as u32);
+                let Some(word) = cpu.view_word(address) else {
+                    continue;
+                };
+                let label = disassembler.labels.get(&address).map(|l| format!("{}:", l)).unwrap_or_default();
+                let marker = marker(address == pc, cpu.view_breakpoints().contains(&address));
+                let line = format!("{} 0x{:x} {:<10} {}", marker, address, label, disassembler.disassemble(word, address));
+                if ui.button(None, line) {
+                    clicked = Some(address);
+                }
+            }
+        });
+    clicked
+}
+
+// the source lines around the one the pc is on, gives back where the code for one starts
+// if it was clicked. lines without any code cant be clicked
+fn describe_source(ui: &mut Ui, cpu: &CPU, assembler: &Assembler) -> Option<u32> {
+    let current = assembler.line_of_address(cpu.get_pc()).unwrap_or(1);
+    let source = assembler.view_source();
+    let mut clicked = None;
+    Group::new(hash!(), vec2(screen_width()/2., 200.))
+        .position(vec2(screen_width()/2. + 20., 350.))
+        .ui(ui, |ui| {
+            for line in current.saturating_sub(4).max(1)..(current + 8).min(source.len() + 1) {
+                let text = &source[line - 1];
+                match assembler.address_of_line(line) {
+                    Some(address) => {
+                        let marker = marker(line == current, cpu.view_breakpoints().contains(&address));
+                        if ui.button(None, format!("{} {:>4} {}", marker, line, text)) {
+                            clicked = Some(address);
+                        }
+                    },
+                    None => ui.label(None, &format!("   {:>4} {}", line, text)),
+                }
+            }
+        });
+    clicked
+}
+
+// show the memory and register contents of the cpu at each step,
+// gives back the address of a byte that got clicked
+fn describe_mem_reg(ui: &mut Ui,cpu: &CPU, abi_names: bool, start: u32) -> Option<u32> {
+    let mut clicked = None;
     Group::new(hash!(), vec2(screen_width()/2., 3200.))
         .position(vec2(10., 50.))
         .ui(ui, |ui| {
             
             
-            let mut i: u32 = 0;
-            for x in cpu.view_registers() {
-                ui.label(None, &format!("x{}: {}", i, *x as i32));
-                i += 1;
+            for (i, x) in cpu.view_registers().iter().enumerate() {
+                let name = if abi_names { abi_name(i as u8).to_string() } else { format!("x{}", i) };
+                ui.label(None, &format!("{}: {}", name, *x as i32));
             }
 
-            let mut j: u32 = 0;
-            for x in cpu.view_memory() {
-                ui.label(vec2(100., 15.*j as f32), &format!("M[{}]: 0x{:x}", j, *x as i32));
-                j += 1;
+            // stops at the end of the address space, bytes that cant be shown are --.
+            // watched ones say what for
+            for j in 0..MEMORY_PAGE {
+                let Some(address) = start.checked_add(j) else { break };
+                let value = cpu.view_byte(address).map_or("--".to_string(), |x| format!("0x{:x}", x));
+                let watch = cpu.view_watchpoints().iter().find(|w| w.covers(address, 1))
+                    .map_or(String::new(), |w| format!(" [{}]", w.kind.name()));
+                if ui.button(vec2(100., 15.*j as f32), format!("M[0x{:x}]: {}{}", address, value, watch)) {
+                    clicked = Some(address);
+                }
             }
         });
+    clicked
+}
+
+// clicking a byte goes through watching it for writes, changes, reads and then not at all
+fn cycle_watchpoint(cpu: &mut CPU, address: u32) {
+    let current = cpu.view_watchpoints().iter().find(|w| w.address == address && w.len == 1).copied();
+    if let Some(watchpoint) = &current {
+        cpu.remove_watchpoint(watchpoint);
+    }
+    let next = match current.map(|w| w.kind) {
+        None => Some(WatchKind::Write),
+        Some(WatchKind::Write) => Some(WatchKind::Change),
+        Some(WatchKind::Change) => Some(WatchKind::Read),
+        Some(WatchKind::Read) => None,
+    };
+    if let Some(kind) = next {
+        cpu.add_watchpoint(Watchpoint::new(address, 1, kind));
+    }
 }
 
 // draws all program names, select and run buttons
@@ -185,39 +441,128 @@ fn draw_main_window(state: &mut AppState) {
                         });
 
 
-                    Group::new(hash!(), vec2(screen_width() - 20., 50.))
+                    Group::new(hash!(), vec2(screen_width() - 20., 100.))
                         .position(vec2(screen_width()/2., 10.))
                         .ui(ui, |ui| {
-                            if let CurrentAction::SelectProgram(p) = &state.cur_state {
-                                if ui.button(None, format!("Run {}", p)) {
-                                    state.cur_state = CurrentAction::RunProgram;
-                                }
+                            // cant run something that didnt assemble
+                            if let CurrentAction::SelectProgram(p) = &state.cur_state
+                                && state.errors.is_empty()
+                                && ui.button(None, format!("Run {}", p)) {
+                                state.cur_state = CurrentAction::RunProgram;
                             }
 
-                            if let CurrentAction::SelectProgram(p) = &state.cur_state {
-                                if ui.button(None, format!("View {}", p)) {
-                                    state.cur_state = CurrentAction::ViewProgram;
-                                }
+                            if let CurrentAction::SelectProgram(p) = &state.cur_state
+                                && state.assembler.is_some()
+                                && ui.button(None, format!("View {}", p)) {
+                                state.cur_state = CurrentAction::ViewProgram;
+                            }
+
+                            // a new layout needs a new cpu, and the program has to go in again.
+                            // presets come with their own syscalls but those can be swapped after
+                            let presets = CpuConfig::presets();
+                            if ui.button(None, format!("Memory: {}", presets[state.preset].0)) {
+                                state.preset = (state.preset + 1) % presets.len();
+                                state.syscalls = presets[state.preset].1.syscalls;
+                                change_cpu(state);
+                            }
+                            if ui.button(None, format!("Syscalls: {}", state.syscalls.name())) {
+                                let all = SyscallAbi::ALL;
+                                state.syscalls = all[(all.iter().position(|abi| *abi == state.syscalls).unwrap() + 1) % all.len()];
+                                change_cpu(state);
                             }
                         });
+
+                    describe_errors(ui, &state.errors);
                 });
         });
 }
 
+// list every problem with the program so they can all be fixed in one go
+fn describe_errors(ui: &mut Ui, errors: &[AssembleError]) {
+    if errors.is_empty() {
+        return;
+    }
+    Group::new(hash!(), vec2(screen_width() - 40., screen_height() - 260.))
+        .position(vec2(10., 220.))
+        .ui(ui, |ui| {
+            ui.label(None, &format!("{} error(s):", errors.len()));
+            for e in errors {
+                ui.label(None, &e.to_string());
+            }
+        });
+}
+
 // deals with loading a program into memory once loaded from the gui
 fn set_program(state: &mut AppState) {
     if let CurrentAction::SelectProgram(n) = &mut state.cur_state {
-        let assembler = Assembler::open_file(&format!("./programs/{}.rv", n));
-        let instrs = assembler.assemble();
-        let mut prgm: Vec<u8> = vec![];
-        for instr in instrs {
-            prgm.push((instr & 0xFF) as u8);
-            prgm.push(((instr & (0xFF << 8)) >> 8) as u8);
-            prgm.push(((instr & (0xFF << 16)) >> 16) as u8);
-            prgm.push(((instr & (0xFF << 24)) >> 24) as u8);
+        state.errors.clear();
+        state.console.clear();
+        state.assembler = None;
+        state.elf = None;
+
+        // anything that isnt assembly has to be an elf
+        if let Ok(bytes) = fs::read(format!("./programs/{}", n))
+            && Elf::is_elf(&bytes) {
+            match Elf::parse(&bytes).and_then(|elf| state.cpu.load_elf(&elf).map(|_| elf)) {
+                Ok(elf) => {
+                    state.disassembler.labels = elf.symbols.iter().map(|(name, address)| (*address, name.clone())).collect();
+                    state.elf = Some(elf);
+                },
+                Err(e) => state.errors.push(AssembleError { line: 0, column: 0, text: n.clone(), message: e.to_string() }),
+            }
+            return;
+        }
+
+        let mut assembler = match Assembler::open_file(&format!("./programs/{}.rv", n)) {
+            Ok(assembler) => assembler,
+            Err(e) => {
+                state.errors.push(e);
+                return;
+            }
+        };
+        assembler.set_base(state.cpu.view_config().reset_vector);
+        match assembler.assemble_bytes() {
+            Ok(prgm) => {
+                if let Err(e) = state.cpu.load_program_sized(&prgm, assembler.view_size()) {
+                    state.errors.push(AssembleError { line: 0, column: 0, text: n.clone(), message: e });
+                }
+            },
+            Err(errors) => state.errors = errors,
         }
+        state.disassembler.labels = assembler.view_labels().into_iter().map(|(name, address)| (address, name)).collect();
         state.assembler = Some(assembler);
-        state.cpu.load_program(&prgm);
+    }
+}
+
+fn new_cpu(config: CpuConfig) -> Result<CPU, String> {
+    let mut cpu = CPU::new(config)?;
+    cpu.set_sandbox(Some(SANDBOX.into()));
+    Ok(cpu)
+}
+
+// makes the cpu again for the picked preset and syscalls, with the program back in it.
+// a layout that doesnt work leaves the old cpu where it is
+fn change_cpu(state: &mut AppState) {
+    let config = CpuConfig::presets()[state.preset].1.syscalls(state.syscalls);
+    match new_cpu(config) {
+        Ok(cpu) => state.cpu = cpu,
+        Err(e) => {
+            state.errors = vec![AssembleError { line: 0, column: 0, text: String::new(), message: e }];
+            return;
+        }
+    }
+    state.memory_view = config.ram_base;
+    set_program(state);
+}
+
+// puts the selected program back into memory, whichever kind it is
+fn reload_program(state: &mut AppState) {
+    if let Some(elf) = &state.elf {
+        // it already fit when it was selected so this cant fail
+        state.cpu.load_elf(elf).ok();
+    } else if let Some(assembler) = &state.assembler
+        && let Ok(prgm) = assembler.assemble_bytes() {
+        state.cpu.load_program_sized(&prgm, assembler.view_size()).ok();
     }
 }
 
@@ -1,97 +1,530 @@
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
-use crate::instruction::InstructionType;
-use crate::instruction::InstructionType::{BInstr, IInstr, RInstr, SInstr};
-
-// i dont know what ebreak actually does and its formed slightly differently than the rest so
-const EBREAK: u32 = 0b00000000000100000000000001110011;
-
-// store type, opcode, funct3, funct7/imm for some
-fn get_info() -> HashMap<&'static str, (InstructionType, u8,u8,u8)> {
-    HashMap::from_iter([
-        ("add", (RInstr, 0b0110011, 0x0, 0x00)),
-        ("sub", (RInstr, 0b0110011, 0x0, 0x20)),
-        ("xor", (RInstr, 0b0110011, 0x4, 0x00)),
-        ("or", (RInstr, 0b0110011, 0x6, 0x00)),
-        ("and", (RInstr, 0b0110011, 0x7, 0x00)),
-        ("sll", (RInstr, 0b0110011, 0x1, 0x00)),
-        ("srl", (RInstr, 0b0110011, 0x5, 0x00)),
-        ("sra", (RInstr, 0b0110011, 0x5, 0x20)),
-        ("addi", (IInstr, 0b0010011, 0x0, 0x00)),
-        ("xori", (IInstr, 0b0010011, 0x4, 0x00)),
-        ("ori", (IInstr, 0b0010011, 0x6, 0x00)),
-        ("andi", (IInstr, 0b0010011, 0x7, 0x00)),
-        ("beq", (BInstr, 0b1100011, 0x0, 0x00)),
-        ("bne", (BInstr, 0b1100011, 0x1, 0x00)),
-        ("blt", (BInstr, 0b1100011, 0x4, 0x00)),
-        ("bge", (BInstr, 0b1100011, 0x5, 0x00)),
-        ("sw", (SInstr, 0b0100011, 0x2, 0x00)),
-        ("lw", (IInstr, 0b0000011, 0x2, 0x00)),
-        ("ebreak", (IInstr, 0b1110011, 0x00, 0x1)),
-    ])
+use std::io::{BufReader, Read};
+use crate::cpu::MEM_START;
+use crate::csr::csr_address;
+use crate::expression::{evaluate, escape, split_hi_lo, Symbols, Value};
+use crate::instruction::{Fields, Format, Instruction};
+use crate::pseudo;
+use crate::register::register_number;
+
+// something wrong with the source, pointing at where it is.
+// line and column start at 1 like an editor, line 0 means it isnt tied to a line (like a missing file)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    pub message: String,
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.line == 0 {
+            return write!(f, "{}", self.message);
+        }
+        write!(f, "line {}:{}: {} ({})", self.line, self.column, self.message, self.text)
+    }
+}
+
+impl Error for AssembleError {}
+
+// which part of the image something goes in, data gets placed right after the code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Text,
+    Data,
+}
+
+// what a data directive puts in memory. values can be labels so they wait until
+// everything has been read before being turned into bytes
+enum Data {
+    Bytes(Vec<u8>),
+    Values(u32, Vec<String>),
+}
+
+struct Directive {
+    line: usize,
+    section: Section,
+    offset: u32,
+    data: Data,
+}
+
+// strips a # comment off a line, unless the # is inside a string or char
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+// label and constant names, so a colon inside a string isnt mistaken for a label
+fn is_symbol(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$')
+}
+
+// splits a list on commas and whitespace, char literals like ' ' or ',' stay in one piece.
+// whitespace only splits two things with no operator between them, so "x1 x2" is two
+// operands but "4 * 2" or "(a + b)" is one
+fn split_list(text: &str) -> Vec<String> {
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut depth = 0;
+
+    let mut push = |part: &mut String| {
+        let trimmed = part.trim();
+        if !trimmed.is_empty() {
+            parts.push(trimmed.to_string());
+        }
+        part.clear();
+    };
+
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            _ if quoted => (),
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' => {
+                push(&mut part);
+                continue;
+            },
+            _ if c.is_whitespace() => {
+                let before = part.trim_end().chars().last();
+                let after = chars[i..].iter().position(|c| !c.is_whitespace()).map(|j| i + j);
+                let ends = |c: char| c.is_ascii_alphanumeric() || "_.$')".contains(c);
+                // % is only the start of something like %hi, otherwise its mod
+                let starts = |j: usize| match chars[j] {
+                    '%' => chars.get(j + 1).is_some_and(|c| c.is_ascii_alphabetic()),
+                    c => c.is_ascii_alphanumeric() || "_.$'".contains(c),
+                };
+                if depth == 0 && before.is_some_and(ends) && after.is_some_and(starts) {
+                    push(&mut part);
+                    continue;
+                }
+            },
+            _ => (),
+        }
+        part.push(c);
+    }
+    push(&mut part);
+    parts
+}
+
+// padding for .align in .text, any bytes before the next word are zero and the words are addi x0, x0, 0
+fn nop_padding(start: u32, size: u32) -> Vec<u8> {
+    let mut bytes = vec![0; size as usize];
+    let mut at = start.next_multiple_of(4) - start;
+    while at + 4 <= size {
+        bytes[at as usize..at as usize + 4].copy_from_slice(&0x13u32.to_le_bytes());
+        at += 4;
+    }
+    bytes
+}
+
+// one or more comma separated "strings" with the usual escapes
+fn parse_strings(text: &str, terminate: bool) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut chars = text.trim().chars().peekable();
+    loop {
+        if chars.next() != Some('"') {
+            return Err(format!("{} is not a valid string", text.trim()));
+        }
+        loop {
+            let c = match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next().and_then(escape) {
+                    Some(c) => c,
+                    None => return Err(format!("{} has an unknown escape", text.trim())),
+                },
+                Some(c) => c,
+                None => return Err(format!("{} is missing its closing quote", text.trim())),
+            };
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+        if terminate {
+            bytes.push(0);
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            None => return Ok(bytes),
+            Some(',') => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+            Some(_) => return Err(format!("{} is not a valid string", text.trim())),
+        }
+    }
+}
+
+// only the .equ constants, for values that have to be known before the labels are
+struct Constants<'a>(&'a HashMap<String, i64>);
+
+impl Symbols for Constants<'_> {
+    fn symbol(&self, name: &str) -> Option<(i64, bool)> {
+        self.0.get(name).map(|v| (*v, false))
+    }
+
+    fn pcrel_lo(&self, _label: &str) -> Result<i64, String> {
+        Err("%pcrel_lo cant be used here".to_string())
+    }
 }
 
 pub struct Assembler {
     program: Vec<String>,
-    // hashmaps cant be made static so just give the assembler one
-    instructions: HashMap<&'static str, (InstructionType, u8,u8,u8)>,
-    
-    // labels are stored with an offset from the start of the program based off instruction count
-    labels: HashMap<String, usize>,
+    // the word each line of program starts at and how many it takes, pseudo instructions can take up more than one
+    offsets: Vec<(usize, usize)>,
+    // which line of the source the code came from, as the offset each run of it starts at.
+    // one per line instead of one per word so a big .space in .text doesnt need millions
+    lines: Vec<(u32, usize)>,
+    // the untouched source lines so errors can point at a column
+    source: Vec<String>,
+    // the address the code gets loaded at, data goes after it
+    base: u32,
+
+    // labels are stored as a byte offset into their section, since where data starts
+    // isnt known until all the code has been read
+    labels: HashMap<String, (Section, u32)>,
+    // names given a value with .equ
+    constants: HashMap<String, i64>,
+    directives: Vec<Directive>,
+    // how many bytes are in each section so far
+    text_size: u32,
+    data_size: u32,
+    // the biggest .align in the data section, the start of it has to line up to that too
+    data_align: u32,
+    // problems found while reading the source, these get reported with the rest in assemble
+    errors: Vec<AssembleError>,
 }
 
 impl Assembler {
+    // where the image gets loaded, the cpu's reset vector. has to be set before assembling
+    // since labels are addresses. defaults to MEM_START
+    pub fn set_base(&mut self, base: u32) {
+        self.base = base;
+    }
+
     // view the program as text
     pub fn view_program(&self) -> &Vec<String> {
         &self.program
     }
 
-    pub fn open_file(filename: &str) -> Assembler {
-        let f = match File::open(filename) {
-            Ok(f) => f,
-            Err(e) => panic!("{}", e),
+    // every label and the address it ends up at, ordered by address
+    pub fn view_labels(&self) -> Vec<(String, u32)> {
+        let mut labels = self.labels.keys()
+            .filter_map(|name| Some((name.clone(), self.label(name)?)))
+            .collect::<Vec<(String, u32)>>();
+        labels.sort_by_key(|(name, address)| (*address, name.clone()));
+        labels
+    }
+
+    // the source as it was written, line 1 is source[0]
+    pub fn view_source(&self) -> &[String] {
+        &self.source
+    }
+
+    // where the code for a source line starts, None if the line doesnt make any
+    pub fn address_of_line(&self, line: usize) -> Option<u32> {
+        self.lines.iter().enumerate().filter(|(_, (_, l))| *l == line).find_map(|(i, (start, _))| {
+            // the first whole word in the run, a few bytes before the next word dont count
+            let end = self.lines.get(i + 1).map_or(self.text_size, |(next, _)| *next);
+            let word = start.next_multiple_of(4);
+            (word < end).then(|| self.base.wrapping_add(word))
+        })
+    }
+
+    // which source line the code at an address came from
+    pub fn line_of_address(&self, address: u32) -> Option<usize> {
+        let offset = address.checked_sub(self.base)?;
+        if !offset.is_multiple_of(4) {
+            return None;
+        }
+        self.line_of_word(offset as usize / 4)
+    }
+
+    // which source line the word at index came from
+    fn line_of_word(&self, index: usize) -> Option<usize> {
+        let offset = index as u64 * 4;
+        if offset >= self.text_size as u64 {
+            return None;
+        }
+        let run = self.lines.partition_point(|(start, _)| *start as u64 <= offset);
+        Some(self.lines[run - 1].1)
+    }
+
+    pub fn open_file(filename: &str) -> Result<Assembler, AssembleError> {
+        let io_error = |e: std::io::Error| AssembleError {
+            line: 0,
+            column: 0,
+            text: filename.to_string(),
+            message: format!("could not read file: {}", e),
         };
+
+        let f = File::open(filename).map_err(io_error)?;
         let mut reader = BufReader::new(f);
         let mut str = String::new();
+        reader.read_to_string(&mut str).map_err(io_error)?;
 
-        if reader.read_to_string(&mut str).is_err() {
-            panic!("Failed to read from file");
+        Ok(Assembler::from_source(&str))
+    }
+
+    // same as open_file but for source thats already in memory
+    pub fn from_source(str: &str) -> Assembler {
+        let mut assembler = Assembler {
+            program: vec![],
+            offsets: vec![],
+            lines: vec![],
+            source: str.lines().map(|s| s.to_string()).collect(),
+            base: MEM_START as u32,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            directives: vec![],
+            text_size: 0,
+            data_size: 0,
+            data_align: 4,
+            errors: vec![],
         };
-        
-        let mut ins_count: usize = 0;
-        let mut labels: HashMap<String, usize> = HashMap::new();
+        let mut section = Section::Text;
+
+        for i in 0..assembler.source.len() {
+            let line = i + 1;
+            // strip comments off each instruction first so comment only lines count as empty
+            let mut s = strip_comment(&assembler.source[i]).trim().to_string();
+
+            // if its a label, we dont include it in the instructions and instead
+            // insert into label hashmap to refer to it later.
+            // anything after the colon is still an instruction
+            if let Some((name, rest)) = s.split_once(':')
+                && is_symbol(name.trim()) {
+                let name = name.trim();
+                if assembler.labels.contains_key(name) || assembler.constants.contains_key(name) {
+                    let e = assembler.error_at(line, name, format!("label {} is defined more than once", name));
+                    assembler.errors.push(e);
+                } else {
+                    let offset = assembler.size(section);
+                    assembler.labels.insert(name.to_string(), (section, offset));
+                }
+                s = rest.trim().to_string();
+            }
 
-        
-        let lines: Vec<String> = str.lines().filter_map(|s|
             if s.is_empty() {
                 // skip over new lines
-                None
-            } else {
-                // if its a label, we dont include it in the instructions and instead
-                // insert into label hashmap to refer to it later
-                if s.contains(":") {
-                    let name = s.split(":").nth(0).unwrap();
-                    labels.insert(name.to_string(), ins_count);
-                    return None;
-                }
-                
-                if s.contains("#") {
-                    // strip comments off each instruction
-                    ins_count += 1;
-                    return Some(s.split("#").nth(0).unwrap().to_string());
+                continue;
+            }
+
+            let (name, args) = s.split_once(char::is_whitespace).unwrap_or((&s, ""));
+            if let Some(directive) = name.strip_prefix('.') {
+                if let Err(e) = assembler.read_directive(line, &mut section, directive, args.trim()) {
+                    assembler.errors.push(e);
                 }
-                
-                ins_count += 1;
-                Some(s.to_string())
+                continue;
+            }
+
+            if section != Section::Text {
+                let e = assembler.error_at(line, name, format!("{} is an instruction, those have to go in .text", name));
+                assembler.errors.push(e);
+                continue;
+            }
+            if !assembler.text_size.is_multiple_of(4) {
+                let e = assembler.error_at(line, name, "instructions have to start on a word boundary, use .align 2 before this".to_string());
+                assembler.errors.push(e);
+                continue;
+            }
 
+            let size = pseudo::expanded_size(name, &Assembler::operands(&s), |v| assembler.constant(v).ok());
+            if size as u64 * 4 > assembler.room() {
+                let e = assembler.error_at(line, name, "the program goes past the end of the address space".to_string());
+                assembler.errors.push(e);
+                continue;
             }
-        ).collect();
+            assembler.offsets.push((assembler.text_size as usize / 4, size));
+            assembler.program.push(s);
+            assembler.grow(Section::Text, size as u32 * 4, line);
+        }
+
+        assembler
+    }
+
+    fn size(&self, section: Section) -> u32 {
+        match section {
+            Section::Text => self.text_size,
+            Section::Data => self.data_size,
+        }
+    }
+
+    // adds bytes to the end of a section, keeping track of the lines the code came from
+    fn grow(&mut self, section: Section, bytes: u32, line: usize) {
+        match section {
+            Section::Text => {
+                if bytes > 0 {
+                    self.lines.push((self.text_size, line));
+                }
+                self.text_size += bytes;
+            },
+            Section::Data => self.data_size += bytes,
+        }
+    }
+
+    // the name is without the dot, args is everything after it
+    fn read_directive(&mut self, line: usize, section: &mut Section, name: &str, args: &str) -> Result<(), AssembleError> {
+        let size = match name {
+            "text" => {
+                *section = Section::Text;
+                return Ok(());
+            },
+            "data" => {
+                *section = Section::Data;
+                return Ok(());
+            },
+            // theres only ever one file so nothing to export to
+            "globl" | "global" => return Ok(()),
+            "equ" | "set" => {
+                let (symbol, value) = match args.split_once(',') {
+                    Some((symbol, value)) if is_symbol(symbol.trim()) => (symbol.trim(), value.trim()),
+                    _ => return Err(self.error_at(line, args, format!(".{} needs a name and a value", name))),
+                };
+                let value = self.constant(value).map_err(|e| self.error_at(line, value, e))?;
+                if self.labels.contains_key(symbol) || self.constants.insert(symbol.to_string(), value).is_some() {
+                    return Err(self.error_at(line, symbol, format!("{} is defined more than once", symbol)));
+                }
+                return Ok(());
+            },
+            "word" | "half" | "byte" => {
+                let values = split_list(args);
+                if values.is_empty() {
+                    return Err(self.error_at(line, args, format!(".{} needs a list of values", name)));
+                }
+                let width = match name {
+                    "word" => 4,
+                    "half" => 2,
+                    _ => 1,
+                };
+                let size = width * values.len() as u32;
+                self.push_data(line, *section, Data::Values(width, values));
+                size
+            },
+            "ascii" | "asciz" | "string" => {
+                let bytes = parse_strings(args, name != "ascii").map_err(|e| self.error_at(line, args, e))?;
+                let size = bytes.len() as u32;
+                self.push_data(line, *section, Data::Bytes(bytes));
+                size
+            },
+            // memory starts zeroed so skipping over it is enough
+            "space" | "zero" => match self.constant(args) {
+                Ok(n) if n >= 0 && n as u64 <= self.room() => n as u32,
+                _ => return Err(self.error_at(line, args, format!("{} is not a valid size", args))),
+            },
+            // .align is a power of two like gnu does it for riscv, .balign is in bytes
+            "align" | "p2align" | "balign" => {
+                let align = match (name, self.constant(args)) {
+                    ("balign", Ok(n)) if n > 0 && (n as u64).is_power_of_two() && n <= 4096 => n as u32,
+                    (_, Ok(n)) if name != "balign" && (0..=12).contains(&n) => 1 << n,
+                    _ => return Err(self.error_at(line, args, format!("{} is not a valid alignment", args))),
+                };
+                let start = self.size(*section);
+                let size = start.next_multiple_of(align) - start;
+                match *section {
+                    Section::Data => self.data_align = self.data_align.max(align),
+                    // code could run into it so whole words get nops, zeros would be illegal
+                    Section::Text => self.push_data(line, Section::Text, Data::Bytes(nop_padding(start, size))),
+                }
+                size
+            },
+            _ => return Err(self.error_at(line, name, format!(".{} is not a known directive", name))),
+        };
 
-        Assembler {
-            program: lines,
-            instructions: get_info(),
-            labels,
+        self.grow(*section, size, line);
+        Ok(())
+    }
+
+    fn push_data(&mut self, line: usize, section: Section, data: Data) {
+        self.directives.push(Directive {
+            line,
+            section,
+            offset: self.size(section),
+            data,
+        });
+    }
+
+    // how many more bytes fit before the program goes past the end of the address space.
+    // growing the code can push the data along by up to its alignment as well
+    fn room(&self) -> u64 {
+        (1u64 << 32).saturating_sub(self.end() + self.data_align as u64)
+    }
+
+    // the address just past the end of the program once loaded
+    fn end(&self) -> u64 {
+        self.base as u64 + self.data_offset() as u64 + self.data_size as u64
+    }
+
+    // how many bytes the program takes up once loaded. the image can be shorter since
+    // any .space at the end isnt in it, memory is zero there already
+    pub fn view_size(&self) -> u32 {
+        self.data_offset() + self.data_size
+    }
+
+    // where the data section starts, relative to the start of the code
+    fn data_offset(&self) -> u32 {
+        self.text_size.next_multiple_of(self.data_align)
+    }
+
+    // the address a label ends up at once loaded
+    fn label(&self, name: &str) -> Option<u32> {
+        self.labels.get(name).map(|(section, offset)| match section {
+            Section::Text => self.base.wrapping_add(*offset),
+            Section::Data => self.base.wrapping_add(self.data_offset() + offset),
+        })
+    }
+
+    // the address of the word at index
+    fn pc(&self, index: usize) -> u32 {
+        self.base.wrapping_add(index as u32 * 4)
+    }
+
+    // an expression that can only use literals and .equ constants that came before it,
+    // for things that change the layout (labels might not be known yet)
+    fn constant(&self, text: &str) -> Result<i64, String> {
+        evaluate(text, 0, &Constants(&self.constants)).map(|v| v.value)
+    }
+
+    // an expression that can use anything, pc is the address of whatever its in
+    fn evaluate(&self, text: &str, pc: u32) -> Result<Value, String> {
+        evaluate(text, pc, self)
+    }
+
+    // makes an error for the word at index, finding text in the source line for the column
+    fn error(&self, index: usize, text: &str, message: String) -> AssembleError {
+        self.error_at(self.line_of_word(index).unwrap(), text, message)
+    }
+
+    fn error_at(&self, line: usize, text: &str, message: String) -> AssembleError {
+        let source = &self.source[line - 1];
+        let column = source.find(text)
+            .or_else(|| source.find(|c: char| !c.is_whitespace()))
+            .unwrap_or(0) + 1;
+
+        AssembleError {
+            line,
+            column,
+            text: text.to_string(),
+            message,
         }
     }
 
@@ -103,67 +536,186 @@ impl Assembler {
     like really i could just make an instruction struct directly
     but thats BORING <3
      */
-    pub fn assemble(&self) -> Vec<u32> {
-        let mut bins: Vec<u32> = vec![];
-        let mut index: usize = 0;
-        for instruction in &self.program {
-            if instruction.contains("ebreak") {
-                bins.push(EBREAK);
-                break;
+    // the whole image as words, see assemble_bytes
+    pub fn assemble(&self) -> Result<Vec<u32>, Vec<AssembleError>> {
+        let bytes = self.assemble_bytes()?;
+        Ok(bytes.chunks(4).map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]])).collect())
+    }
+
+    // the code followed by the data, laid out little endian ready to go into memory.
+    // it stops after the last thing with a value, so reserving space at the end costs nothing.
+    // every bad line gets reported, not just the first one
+    pub fn assemble_bytes(&self) -> Result<Vec<u8>, Vec<AssembleError>> {
+        let data_offset = self.data_offset();
+        let mut errors = self.errors.clone();
+        // the base can be moved after the source was read so it might not fit anymore
+        if self.end() > 1 << 32 {
+            errors.push(AssembleError {
+                line: 0,
+                column: 0,
+                text: String::new(),
+                message: format!("the program goes past the end of the address space when loaded at 0x{:x}", self.base),
+            });
+            return Err(errors);
+        }
+
+        let code = self.offsets.last().map_or(0, |(index, size)| (index + size) * 4);
+        let data = self.directives.iter().map(|directive| {
+            let start = match directive.section {
+                Section::Text => directive.offset,
+                Section::Data => data_offset + directive.offset,
+            } as usize;
+            start + match &directive.data {
+                Data::Bytes(bytes) => bytes.len(),
+                Data::Values(width, values) => *width as usize * values.len(),
             }
+        }).max().unwrap_or(0);
+        let mut image = vec![0u8; code.max(data).next_multiple_of(4)];
+
+        for (i, line) in self.program.iter().enumerate() {
+            let (index, size) = self.offsets[i];
+            let name = line.split_ascii_whitespace().next().unwrap();
+
+            // pseudo instructions turn into one or more real ones, everything else is as is
+            let pc = self.pc(index);
+            let lines = pseudo::expand(name, &Assembler::operands(line), pc, size, |v| self.evaluate(v, pc).map(|v| v.value));
+            let lines = match lines {
+                Ok(Some(lines)) => lines,
+                Ok(None) => vec![line.clone()],
+                Err((text, message)) => {
+                    errors.push(self.error(index, &text, message));
+                    continue;
+                }
+            };
 
-            let name = instruction.split_ascii_whitespace().next();
+            for (j, instruction) in lines.iter().enumerate() {
+                match self.assemble_instruction(instruction, index + j) {
+                    Ok(bin) => {
+                        let at = (index + j) * 4;
+                        image[at..at + 4].copy_from_slice(&bin.to_le_bytes());
+                    },
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
 
-            if name.is_none() {
-                break;
+        for directive in &self.directives {
+            let start = match directive.section {
+                Section::Text => directive.offset,
+                Section::Data => data_offset + directive.offset,
+            } as usize;
+            match self.data_bytes(directive, self.base.wrapping_add(start as u32)) {
+                Ok(bytes) => image[start..start + bytes.len()].copy_from_slice(&bytes),
+                Err(e) => errors.push(e),
             }
+        }
 
-            let val = match self.instructions.get(name.unwrap()) {
-                Some(val) => val,
-                None => panic!("Instruction {} not found", name.unwrap()),
-            };
+        if !errors.is_empty() {
+            errors.sort_by_key(|e| (e.line, e.column));
+            return Err(errors);
+        }
+        Ok(image)
+    }
 
-            let bin = match &val.0 {
-                RInstr => {
-                    self.info_to_r(val, &self.extract_vals(instruction))
-                },
-                IInstr => {
-                    self.info_to_i(val, &self.extract_vals_i(instruction, index))
-                },
-                SInstr => {
-                    self.info_to_s(val, &self.extract_vals_i(instruction, index))
-                },
-                BInstr => {
-                    self.info_to_b(val, &self.extract_vals_i(instruction, index))
-                },
-                //    InstructionType::UInstr => 0,
-                //    InstructionType::JInstr => 0,
-            };
-            bins.push(bin);
-            index += 1;
+    // values for .word and friends can be any expression, labels are their address
+    fn data_bytes(&self, directive: &Directive, address: u32) -> Result<Vec<u8>, AssembleError> {
+        let (width, values) = match &directive.data {
+            Data::Bytes(bytes) => return Ok(bytes.clone()),
+            Data::Values(width, values) => (*width, values),
+        };
+        // anything that fits signed or unsigned is fine
+        let range = -(1i64 << (width * 8 - 1))..(1i64 << (width * 8));
+
+        let mut bytes = vec![];
+        for text in values {
+            let value = self.evaluate(text, address).map_err(|e| self.error_at(directive.line, text, e))?.value;
+            if !range.contains(&value) {
+                return Err(self.error_at(directive.line, text, format!("{} does not fit in {} bits", text, width * 8)));
+            }
+            bytes.extend_from_slice(&(value as u32).to_le_bytes()[..width as usize]);
         }
-        for f in &bins {
-            println!("{:b}",f);
+        Ok(bytes)
+    }
+
+    // turns one real instruction into its word, index is which word of the output it is
+    fn assemble_instruction(&self, instruction: &str, index: usize) -> Result<u32, AssembleError> {
+        let name = instruction.split_ascii_whitespace().next().unwrap_or_default();
+        let unknown = || self.error(index, name, format!("unknown instruction {}", name));
+
+        let format = Instruction::format_of(name).ok_or_else(unknown)?;
+        let fields = match format {
+            Format::R => {
+                let (rd, rs1, rs2) = self.extract_vals(instruction, index)?;
+                Fields { rd, rs1, rs2, imm: 0 }
+            },
+            Format::I | Format::Load => {
+                let (rd, rs1, imm) = self.extract_vals_i(instruction, index)?;
+                Fields { rd, rs1, rs2: 0, imm: imm as i32 }
+            },
+            Format::Shift => {
+                let (rd, rs1, imm) = self.extract_vals_i(instruction, index)?;
+                Fields { rd, rs1, rs2: 0, imm: self.check_shift(imm, instruction, index)? }
+            },
+            // stores are written rs2, imm(rs1)
+            Format::Store => {
+                let (rs2, rs1, imm) = self.extract_vals_i(instruction, index)?;
+                Fields { rd: 0, rs1, rs2, imm: imm as i32 }
+            },
+            Format::Branch => {
+                let (rs1, rs2, imm) = self.extract_vals_b(instruction, index)?;
+                Fields { rd: 0, rs1, rs2, imm }
+            },
+            Format::Upper => {
+                let (rd, imm) = self.extract_vals_u(instruction, index)?;
+                Fields { rd, imm: imm << 12, ..Fields::default() }
+            },
+            Format::Jump => {
+                let (rd, imm) = self.extract_vals_j(instruction, index)?;
+                Fields { rd, imm, ..Fields::default() }
+            },
+            // csr instructions are i type but the imm is the csr and it can be a name
+            Format::Csr | Format::CsrImm => {
+                let (rd, csr, src) = self.extract_vals_csr(instruction, index, format == Format::CsrImm)?;
+                Fields { rd, rs1: src, rs2: 0, imm: csr as i32 }
+            },
+            // these have no operands, theyre always the same word
+            Format::System => {
+                let parts = Assembler::operands(instruction);
+                if !parts.is_empty() {
+                    return Err(self.error(index, instruction, format!("{} expects 0 operand(s) but found {}", name, parts.len())));
+                }
+                Fields::default()
+            },
+        };
+
+        Instruction::new(name, fields).map(|i| i.encode()).ok_or_else(unknown)
+    }
+
+    // splits the operands up on commas and whitespace, so "x1, x2,x3" is [x1, x2, x3]
+    fn operands(str: &str) -> Vec<String> {
+        let rest = str.trim_start().split_once(char::is_whitespace).map(|(_, rest)| rest).unwrap_or("");
+        split_list(rest)
+    }
+
+    // registers can be written as x0..x31 or by their abi name (sp, a0, ...)
+    fn register(&self, str: &str, index: usize) -> Result<u8, AssembleError> {
+        match register_number(str) {
+            Some(r) => Ok(r),
+            None => Err(self.error(index, str, format!("{} is not a valid register", str))),
         }
-        bins
     }
 
-    // both extract functions take an instruction in riscv assembly and return the destinations inside
+    // all the extract functions take an instruction in riscv assembly and return the destinations inside
     
     // extracts instructions that have comma seperated list of 3 values
     // usually of form rd, rs1, rs2 (can not include an imm value)
-    fn extract_vals(&self, str: &String) -> (u8, u8, u8) {
-        let parts = str.split_ascii_whitespace().skip(1).filter_map(|s|  {
-            s.replace('x',"").replace(',',"").parse::<u8>().ok()
-        }).collect::<Vec<u8>>();
+    fn extract_vals(&self, str: &str, index: usize) -> Result<(u8, u8, u8), AssembleError> {
+        let parts = Assembler::operands(str);
         if parts.len() != 3 {
-            panic!("Malformed r instruction {}", str);
+            return Err(self.error(index, str, format!("expected 3 registers but found {}", parts.len())));
         }
 
-        if parts[0] >= 32 || parts[1] >= 32 || parts[2] >= 32 {
-            panic!("Malformed r instruction {}: invalid register(s) || {:?}", str, parts);
-        }
-        (parts[0], parts[1], parts[2])
+        Ok((self.register(&parts[0], index)?, self.register(&parts[1], index)?, self.register(&parts[2], index)?))
     }
 
     /*
@@ -174,92 +726,165 @@ impl Assembler {
 // imm has to be stored there
      */
 
-    fn extract_vals_i(&self, str: &String, index: usize) -> (u8, u8, i16) {
-        // we will deal with everything as an i16 until we know which is the proper imm value
-        let mut parts = str.replace('('," ").replace(')',"").split_ascii_whitespace().skip(1).filter_map(|s|  {
-            let val = s.replace('x',"").replace(',',"");
-            if self.labels.contains_key(&val) {
-                return Some((self.labels.get(&val).unwrap().clone() as i16 - index as i16) * 4)
-            }
-            val.parse::<i16>().ok()
-        }).collect::<Vec<i16>>();
+    // works out an immediate for the word at index. relative is for branches and jumps, where
+    // an address (anything with a label in it) becomes an offset from this instruction
+    fn immediate(&self, text: &str, index: usize, relative: bool) -> Result<i64, AssembleError> {
+        let pc = self.pc(index);
+        match self.evaluate(text, pc) {
+            Ok(Value { value, address: true }) if relative => Ok(value - pc as i64),
+            Ok(Value { value, .. }) => Ok(value),
+            Err(e) => Err(self.error(index, text, e)),
+        }
+    }
+
+    fn extract_vals_i(&self, str: &str, index: usize) -> Result<(u8, u8, i16), AssembleError> {
+        let mut parts = Assembler::operands(str);
 
-        // paranthesis only can be in load/store instructions so its fine to just swap like this
-        if str.contains("(") {
-            let t = parts[1];
-            parts[1]=parts[2];
-            parts[2]=t;
+        // loads and stores are written imm(rs1), split that back up into rs1 then imm.
+        // an empty imm like (x1) is just 0
+        if parts.len() == 2
+            && let Some((imm, rs1)) = parts[1].strip_suffix(')').and_then(|p| p.rsplit_once('(')) {
+            let imm = if imm.trim().is_empty() { "0" } else { imm.trim() };
+            parts = vec![parts[0].clone(), rs1.to_string(), imm.to_string()];
         }
-        // register destinations are stored in the first two u8s, so make sure it is within range
-        if parts[0] >= 32 || parts[0] < 0 || parts[1] >= 32 || parts[1] < 0 {
-            panic!("Malformed i instruction {}: invalid register(s) || {:?}", str, parts);
+        if parts.len() != 3 {
+            return Err(self.error(index, str, format!("expected 3 operands but found {}", parts.len())));
         }
-        
+
+        // register destinations are the first two, imm is always last
+        let r1 = self.register(&parts[0], index)?;
+        let r2 = self.register(&parts[1], index)?;
+
+        let imm = self.immediate(&parts[2], index, false)?;
+
         // imm has to fit within 12 bit signed int
-        if parts[2] < -2048 || parts[2] > 2047 {
-            panic!("{} is not a valid imm value", parts[2]);
+        if !(-2048..=2047).contains(&imm) {
+            return Err(self.error(index, &parts[2], format!("{} is not a valid imm value, it has to fit in 12 bits", parts[2])));
         }
 
-        (parts[0] as u8, parts[1] as u8, parts[2])
+        Ok((r1, r2, imm as i16))
+    }
+
+    // immediate shifts only get 5 bits of shamt
+    fn check_shift(&self, shamt: i16, str: &str, index: usize) -> Result<i32, AssembleError> {
+        if !(0..32).contains(&shamt) {
+            let text = Assembler::operands(str).pop().unwrap_or_default();
+            return Err(self.error(index, &text, format!("{} is not a valid shift amount, it has to be 0 to 31", text)));
+        }
+        Ok(shamt as i32)
     }
     
-    // takes information about an instruction in and converts it into its binary form
+    // u instructions are just rd, imm where imm is the upper 20 bits
+    // so it can be 0..=0xFFFFF (or negative if you want the sign bit set)
+    fn extract_vals_u(&self, str: &str, index: usize) -> Result<(u8, i32), AssembleError> {
+        let parts = Assembler::operands(str);
+        if parts.len() != 2 {
+            return Err(self.error(index, str, format!("expected 2 operands but found {}", parts.len())));
+        }
+
+        let rd = self.register(&parts[0], index)?;
 
-    fn info_to_r(&self, info: &(InstructionType, u8, u8, u8), registers: &(u8, u8, u8)) -> u32 {
-        let mut binary = (info.1 & 0x7F) as u32; // opcode
-        binary |= ((registers.0 & 0x1F) as u32) << 7; // rd
-        binary |= ((info.2 & 0x7) as u32) << 12; // funct3
-        binary |= ((registers.1 & 0x1F) as u32) << 15; // rs1
-        binary |= ((registers.2 & 0x1F) as u32) << 20; //rs2
-        binary |= ((info.3 & 0x7F) as u32) << 25; // funct7
+        let imm = match self.immediate(&parts[1], index, false)? {
+            imm if (-0x80000..=0xFFFFF).contains(&imm) => imm as i32,
+            _ => return Err(self.error(index, &parts[1], format!("{} is not a valid imm value, it has to fit in 20 bits", parts[1]))),
+        };
 
-        binary
+        Ok((rd, imm))
     }
 
-    fn info_to_i(&self, info: &(InstructionType, u8, u8, u8), data: &(u8, u8, i16)) -> u32 {
-        let mut binary = (info.1 & 0x7F) as u32; // opcode
-        binary |= ((info.2 & 0x7) as u32) << 12; // funct3
+    // rd, csr, rs1 or rd, csr, uimm for the i versions (uimm is only 5 bits)
+    // csr can be a name like mtvec or just the number
+    fn extract_vals_csr(&self, str: &str, index: usize, imm: bool) -> Result<(u8, u16, u8), AssembleError> {
+        let parts = Assembler::operands(str);
+        if parts.len() != 3 {
+            return Err(self.error(index, str, format!("expected 3 operands but found {}", parts.len())));
+        }
 
-        binary |= ((data.0 & 0x1F) as u32) << 7; // rd
-        binary |= ((data.1 & 0x1F) as u32) << 15; // rs1
-        binary |= ((data.2 & 0xFFF) as u32) << 20; // imm
+        let rd = self.register(&parts[0], index)?;
 
-        binary
-    }
+        let csr = match csr_address(&parts[1]) {
+            Some(csr) => csr,
+            None => match self.evaluate(&parts[1], self.pc(index)) {
+                Ok(csr) if (0..0x1000).contains(&csr.value) => csr.value as u16,
+                _ => return Err(self.error(index, &parts[1], format!("{} is not a valid csr", parts[1]))),
+            },
+        };
 
+        let src = if imm {
+            match self.immediate(&parts[2], index, false)? {
+                src if (0..32).contains(&src) => src as u8,
+                _ => return Err(self.error(index, &parts[2], format!("{} is not a valid uimm, it has to be 0 to 31", parts[2]))),
+            }
+        } else {
+            self.register(&parts[2], index)?
+        };
 
-    fn info_to_s(&self, info: &(InstructionType, u8, u8, u8), data: &(u8, u8, i16)) -> u32 {
-        let mut binary = (info.1 & 0x7F) as u32; // opcode
-        let immp1 = (data.2 as u32) & 0x1F; // imm values are split in two places 
-        let immp2 = data.2 as u32 >> 5 & 0x7F;
+        Ok((rd, csr, src))
+    }
 
+    // rs1, rs2, target where target is a label (or expression with one) or a byte offset
+    // from this instruction, like jumps
+    fn extract_vals_b(&self, str: &str, index: usize) -> Result<(u8, u8, i32), AssembleError> {
+        let parts = Assembler::operands(str);
+        if parts.len() != 3 {
+            return Err(self.error(index, str, format!("expected 3 operands but found {}", parts.len())));
+        }
 
-        binary |= immp1 << 7;
+        let rs1 = self.register(&parts[0], index)?;
+        let rs2 = self.register(&parts[1], index)?;
 
-        binary |= ((info.2 & 0x7) as u32) << 12; // funct3
-        binary |= ((data.1 & 0x1F) as u32) << 15; // rs1
-        binary |= ((data.0 & 0x1F) as u32) << 20; //rs2
+        let imm = self.immediate(&parts[2], index, true)?;
 
-        binary |= (immp2 & 0x7F) << 25;
+        // 13 bit signed and has to land on an even address
+        if !(-0x1000..=0xFFE).contains(&imm) || imm % 2 != 0 {
+            return Err(self.error(index, &parts[2], format!("{} is not a valid branch offset, it has to be even and fit in 13 bits", parts[2])));
+        }
 
-        binary
+        Ok((rs1, rs2, imm as i32))
     }
 
-    fn info_to_b(&self, info: &(InstructionType, u8, u8, u8), data: &(u8, u8, i16)) -> u32 {
-        let mut binary = (info.1 & 0x7F) as u32; // opcode
-        let immp1 = ((data.2>>1 & 0xF) << 1 | (data.2 >> 11 & 0x1)) as u8;
-        let immp2 = (((data.2 >> 12 & 0x1) << 6) | ((data.2) >> 5) & 0x3F) as u8;
-
+    // jal rd, target or just jal target (which links into x1 like normal)
+    // target is a label (or expression with one) or a byte offset from this instruction
+    fn extract_vals_j(&self, str: &str, index: usize) -> Result<(u8, i32), AssembleError> {
+        let parts = Assembler::operands(str);
+        let (rd, target) = match parts.len() {
+            1 => (1, &parts[0]),
+            2 => (self.register(&parts[0], index)?, &parts[1]),
+            _ => return Err(self.error(index, str, format!("expected 1 or 2 operands but found {}", parts.len()))),
+        };
 
-        binary |= (immp1 as u32) << 7;
+        let imm = self.immediate(target, index, true)?;
 
-        binary |= ((info.2 & 0x7) as u32) << 12; // funct3
-        binary |= ((data.0 & 0x1F) as u32) << 15; // rs1
-        binary |= ((data.1 & 0x1F) as u32) << 20; //rs2
+        // 21 bit signed and has to land on an even address
+        if !(-0x100000..=0xFFFFF).contains(&imm) || imm % 2 != 0 {
+            return Err(self.error(index, target, format!("{} is not a valid jump offset, it has to be even and fit in 21 bits", target)));
+        }
 
-        binary |= (immp2 as u32) << 25;
+        Ok((rd, imm as i32))
+    }
+}
 
+impl Symbols for Assembler {
+    fn symbol(&self, name: &str) -> Option<(i64, bool)> {
+        match self.constants.get(name) {
+            Some(value) => Some((*value, false)),
+            None => self.label(name).map(|address| (address as i64, true)),
+        }
+    }
 
-        binary
+    // label has to be on an auipc using %pcrel_hi, the low part is whatever that auipc
+    // didnt cover of its target (relative to the auipc, not the instruction using this)
+    fn pcrel_lo(&self, label: &str) -> Result<i64, String> {
+        let auipc = self.label(label).ok_or(format!("{} is not a known label", label))?;
+        let index = (auipc.wrapping_sub(self.base) / 4) as usize;
+        let target = self.offsets.iter().position(|(o, _)| *o == index)
+            .map(|i| &self.program[i])
+            .filter(|line| line.split_ascii_whitespace().next() == Some("auipc"))
+            .and_then(|line| Assembler::operands(line).pop())
+            .and_then(|hi| hi.strip_prefix("%pcrel_hi(")?.strip_suffix(')').map(|t| t.to_string()))
+            .ok_or(format!("{} is not the label of an auipc with %pcrel_hi", label))?;
+
+        let target = self.evaluate(&target, auipc)?.value;
+        Ok(split_hi_lo((target as i32).wrapping_sub(auipc as i32)).1 as i64)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,298 @@
+use std::fmt::{Display, Formatter};
+
+// why an access didnt work, the cpu turns these into a load, store or fetch access fault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusError {
+    // nothing is attached there
+    Unmapped(u32),
+    // something is there but it doesnt allow that, like writing to rom
+    Denied(u32),
+}
+
+impl BusError {
+    pub fn address(&self) -> u32 {
+        match self {
+            BusError::Unmapped(address) | BusError::Denied(address) => *address,
+        }
+    }
+
+    // devices only see offsets into their region, this puts the region back on
+    fn offset_by(self, base: u32) -> BusError {
+        match self {
+            BusError::Unmapped(address) => BusError::Unmapped(address.wrapping_add(base)),
+            BusError::Denied(address) => BusError::Denied(address.wrapping_add(base)),
+        }
+    }
+}
+
+impl Display for BusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BusError::Unmapped(address) => write!(f, "nothing is mapped at 0x{:x}", address),
+            BusError::Denied(address) => write!(f, "access denied at 0x{:x}", address),
+        }
+    }
+}
+
+// anything that can be read and written a byte, half or word at a time. the memory map is
+// one of these and so is everything attached to it, devices just get addresses relative
+// to where they were attached. alignment is the cpus problem, not the bus's
+pub trait Bus {
+    fn read_byte(&mut self, address: u32) -> Result<u8, BusError>;
+    fn write_byte(&mut self, address: u32, value: u8) -> Result<(), BusError>;
+
+    // reading without any side effects, for showing memory in the ui. None if theres nothing
+    // there or reading would change something (like taking a byte out of a fifo)
+    fn peek(&self, address: u32) -> Option<u8>;
+
+    // plain storage, where reading gives back what was written and writing has no other effects.
+    // devices arent, peeking a register and writing the value back can do something else
+    fn is_memory(&self) -> bool {
+        false
+    }
+
+    // the bigger ones are little endian and made of byte accesses unless something does them itself
+    fn read_half(&mut self, address: u32) -> Result<u16, BusError> {
+        Ok(u16::from_le_bytes([self.read_byte(address)?, self.read_byte(address.wrapping_add(1))?]))
+    }
+
+    fn read_word(&mut self, address: u32) -> Result<u32, BusError> {
+        let mut bytes = [0; 4];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.read_byte(address.wrapping_add(i as u32))?;
+        }
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn write_half(&mut self, address: u32, value: u16) -> Result<(), BusError> {
+        for (i, byte) in value.to_le_bytes().into_iter().enumerate() {
+            self.write_byte(address.wrapping_add(i as u32), byte)?;
+        }
+        Ok(())
+    }
+
+    fn write_word(&mut self, address: u32, value: u32) -> Result<(), BusError> {
+        for (i, byte) in value.to_le_bytes().into_iter().enumerate() {
+            self.write_byte(address.wrapping_add(i as u32), byte)?;
+        }
+        Ok(())
+    }
+
+    // putting a program in place. unlike writing this works on rom too
+    fn load(&mut self, address: u32, bytes: &[u8]) -> Result<(), BusError> {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.write_byte(address.wrapping_add(i as u32), *byte)?;
+        }
+        Ok(())
+    }
+}
+
+// plain memory
+pub struct Ram {
+    bytes: Vec<u8>,
+}
+
+impl Ram {
+    pub fn new(size: u32) -> Ram {
+        Ram { bytes: vec![0; size as usize] }
+    }
+
+    // where size bytes at address are, if theyre all in here
+    fn range(&self, address: u32, size: usize) -> Result<std::ops::Range<usize>, BusError> {
+        let start = address as usize;
+        match start.checked_add(size) {
+            Some(end) if end <= self.bytes.len() => Ok(start..end),
+            _ => Err(BusError::Unmapped(address)),
+        }
+    }
+}
+
+// does the half and word ones itself since every instruction fetch comes through here
+impl Bus for Ram {
+    fn read_byte(&mut self, address: u32) -> Result<u8, BusError> {
+        Ok(self.bytes[self.range(address, 1)?][0])
+    }
+
+    fn write_byte(&mut self, address: u32, value: u8) -> Result<(), BusError> {
+        let range = self.range(address, 1)?;
+        self.bytes[range][0] = value;
+        Ok(())
+    }
+
+    fn peek(&self, address: u32) -> Option<u8> {
+        self.bytes.get(address as usize).copied()
+    }
+
+    fn is_memory(&self) -> bool {
+        true
+    }
+
+    fn read_half(&mut self, address: u32) -> Result<u16, BusError> {
+        let b = &self.bytes[self.range(address, 2)?];
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn read_word(&mut self, address: u32) -> Result<u32, BusError> {
+        let b = &self.bytes[self.range(address, 4)?];
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn write_half(&mut self, address: u32, value: u16) -> Result<(), BusError> {
+        let range = self.range(address, 2)?;
+        self.bytes[range].copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn write_word(&mut self, address: u32, value: u32) -> Result<(), BusError> {
+        let range = self.range(address, 4)?;
+        self.bytes[range].copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn load(&mut self, address: u32, bytes: &[u8]) -> Result<(), BusError> {
+        let range = self.range(address, bytes.len())?;
+        self.bytes[range].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+// memory that programs can only read, it still gets filled in by load
+pub struct Rom {
+    ram: Ram,
+}
+
+impl Rom {
+    pub fn new(size: u32) -> Rom {
+        Rom { ram: Ram::new(size) }
+    }
+}
+
+impl Bus for Rom {
+    fn read_byte(&mut self, address: u32) -> Result<u8, BusError> {
+        self.ram.read_byte(address)
+    }
+
+    fn write_byte(&mut self, address: u32, _value: u8) -> Result<(), BusError> {
+        Err(BusError::Denied(address))
+    }
+
+    fn peek(&self, address: u32) -> Option<u8> {
+        self.ram.peek(address)
+    }
+
+    fn is_memory(&self) -> bool {
+        true
+    }
+
+    fn read_word(&mut self, address: u32) -> Result<u32, BusError> {
+        self.ram.read_word(address)
+    }
+
+    fn load(&mut self, address: u32, bytes: &[u8]) -> Result<(), BusError> {
+        self.ram.load(address, bytes)
+    }
+}
+
+// one thing attached to the map and the addresses it answers to
+struct Region {
+    name: String,
+    base: u32,
+    size: u32,
+    device: Box<dyn Bus>,
+}
+
+impl Region {
+    // one past the end, can be 4GiB
+    fn end(&self) -> u64 {
+        self.base as u64 + self.size as u64
+    }
+}
+
+// every access gets sent to whatever region its in, with the address made relative to it.
+// an access has to fit in one region, one that runs off the end is unmapped
+#[derive(Default)]
+pub struct MemoryMap {
+    regions: Vec<Region>,
+}
+
+impl MemoryMap {
+    pub fn new() -> MemoryMap {
+        MemoryMap::default()
+    }
+
+    // regions cant overlap or go past the end of the address space
+    pub fn attach(&mut self, name: &str, base: u32, size: u32, device: Box<dyn Bus>) -> Result<(), String> {
+        let end = base as u64 + size as u64;
+        if size == 0 || end > 1 << 32 {
+            return Err(format!("{} at 0x{:x} with 0x{:x} bytes does not fit in the address space", name, base, size));
+        }
+        if let Some(other) = self.regions.iter().find(|r| (base as u64) < r.end() && end > r.base as u64) {
+            return Err(format!("{} at 0x{:x} overlaps {} at 0x{:x}", name, base, other.name, other.base));
+        }
+        self.regions.push(Region { name: name.to_string(), base, size, device });
+        self.regions.sort_by_key(|r| r.base);
+        Ok(())
+    }
+
+    // name, base and size of everything attached, in address order
+    pub fn regions(&self) -> impl Iterator<Item = (&str, u32, u32)> {
+        self.regions.iter().map(|r| (r.name.as_str(), r.base, r.size))
+    }
+
+    // whether size bytes at address are all in the same region
+    pub fn contains(&self, address: u32, size: u32) -> bool {
+        self.find(address, size).is_some()
+    }
+
+    // whether the byte at address is ram or rom rather than a device
+    pub fn is_memory_at(&self, address: u32) -> bool {
+        self.find(address, 1).is_some_and(|i| self.regions[i].device.is_memory())
+    }
+
+    fn find(&self, address: u32, size: u32) -> Option<usize> {
+        let end = address as u64 + size as u64;
+        self.regions.iter().position(|r| address >= r.base && end <= r.end())
+    }
+
+    // runs an access on the right device, errors come back with the full address
+    fn access<T>(&mut self, address: u32, size: u32, f: impl FnOnce(&mut dyn Bus, u32) -> Result<T, BusError>) -> Result<T, BusError> {
+        let index = self.find(address, size).ok_or(BusError::Unmapped(address))?;
+        let region = &mut self.regions[index];
+        f(region.device.as_mut(), address - region.base).map_err(|e| e.offset_by(region.base))
+    }
+}
+
+impl Bus for MemoryMap {
+    fn read_byte(&mut self, address: u32) -> Result<u8, BusError> {
+        self.access(address, 1, |d, a| d.read_byte(a))
+    }
+
+    fn write_byte(&mut self, address: u32, value: u8) -> Result<(), BusError> {
+        self.access(address, 1, |d, a| d.write_byte(a, value))
+    }
+
+    fn peek(&self, address: u32) -> Option<u8> {
+        let region = &self.regions[self.find(address, 1)?];
+        region.device.peek(address - region.base)
+    }
+
+    fn read_half(&mut self, address: u32) -> Result<u16, BusError> {
+        self.access(address, 2, |d, a| d.read_half(a))
+    }
+
+    fn read_word(&mut self, address: u32) -> Result<u32, BusError> {
+        self.access(address, 4, |d, a| d.read_word(a))
+    }
+
+    fn write_half(&mut self, address: u32, value: u16) -> Result<(), BusError> {
+        self.access(address, 2, |d, a| d.write_half(a, value))
+    }
+
+    fn write_word(&mut self, address: u32, value: u32) -> Result<(), BusError> {
+        self.access(address, 4, |d, a| d.write_word(a, value))
+    }
+
+    fn load(&mut self, address: u32, bytes: &[u8]) -> Result<(), BusError> {
+        self.access(address, bytes.len() as u32, |d, a| d.load(a, bytes))
+    }
+}
@@ -0,0 +1,343 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use riscvemulator::{Assembler, CpuConfig, Disassembler, Elf, SyscallAbi, WatchKind, Watchpoint, CPU};
+use riscvemulator::register::abi_name;
+
+// how many instructions to run before giving up on a program that never stops
+const DEFAULT_LIMIT: u64 = 1_000_000;
+
+// exit codes so scripts can tell what happened. a program's own exit code gets passed
+// through, so the emulator's are kept out of the way of the small ones programs use
+const EXIT_OK: i32 = 0;
+const EXIT_FAULT: i32 = 121;
+const EXIT_WATCH: i32 = 122;
+// also used when an elf cant be loaded
+const EXIT_ASSEMBLE: i32 = 123;
+// same as timeout
+const EXIT_LIMIT: i32 = 124;
+const EXIT_USAGE: i32 = 125;
+// what a program exiting with one of the emulator's codes gets instead
+const EXIT_RESERVED: i32 = 120;
+
+// how many instructions to run between printing what came out of the uart
+const OUTPUT_CHUNK: u64 = 10_000;
+
+const USAGE: &str = "usage: riscvemulator <program> [--limit N] [--mem ADDR:LEN]... [--trace] [--abi] [--preset NAME]
+                     [--syscalls ABI] [--sandbox DIR] [--watch ADDR:LEN[:KIND]]...
+  runs the program without opening a window until ebreak, a fault, a watchpoint or N instructions.
+  the program is either assembly or a statically linked rv32 elf executable.
+  anything sent on the uart is printed as it goes, and stdin is what gets typed into it
+  --limit N        stop after N instructions (default 1000000)
+  --mem ADDR:LEN   print LEN bytes of memory starting at ADDR once it stops (repeatable)
+  --trace          print every instruction as it runs
+  --abi            use abi register names (sp, a0...) in the output
+  --preset NAME    memory layout, one of tiny (the default, 512 bytes), small (64KiB),
+                   elf (16MiB with code at 0x10000) or virt (128MiB at 0x80000000)
+  --syscalls ABI   what ecall does, one of rars (the default), linux (the default for the
+                   elf preset) or none to always trap. with rars or linux only the numbers
+                   they dont know trap, even once the program has a trap handler
+  --sandbox DIR    the directory programs can open files in with the linux abi
+  --watch ADDR:LEN[:KIND]
+                   stop when the program touches LEN bytes at ADDR (repeatable). KIND is
+                   write (the default), read or change for writes that change the value
+exit status: 0 after ebreak, or the program's own code (the low 8 bits) if it ended with an
+  exit ecall. the emulator's are 121 fault, 122 watchpoint, 123 couldnt assemble or load,
+  124 hit the limit and 125 bad arguments. a program exiting with 121 to 125 gets 120
+with no arguments the gui opens instead";
+
+struct Options {
+    file: String,
+    limit: u64,
+    regions: Vec<(u32, u32)>,
+    trace: bool,
+    abi: bool,
+    config: CpuConfig,
+    sandbox: Option<PathBuf>,
+    watchpoints: Vec<Watchpoint>,
+}
+
+// numbers can be given as decimal or 0x hex
+fn parse_num(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse::<u64>().ok(),
+    }
+}
+
+// ADDR:LEN, with anything after another : handed back as well
+fn parse_region(region: &str) -> Option<(u32, u32, Option<&str>)> {
+    let mut parts = region.splitn(3, ':');
+    let addr = parse_num(parts.next()?)?;
+    let len = parse_num(parts.next()?)?;
+    if addr > u32::MAX as u64 || len > u32::MAX as u64 {
+        return None;
+    }
+    Some((addr as u32, len as u32, parts.next()))
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut file = None;
+    let mut limit = DEFAULT_LIMIT;
+    let mut regions = vec![];
+    let mut trace = false;
+    let mut abi = false;
+    let mut config = CpuConfig::default();
+    let mut syscalls = None;
+    let mut sandbox = None;
+    let mut watchpoints = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limit" => {
+                let n = args.next().ok_or("--limit needs a value")?;
+                limit = parse_num(n).ok_or(format!("{} is not a valid limit", n))?;
+            },
+            "--mem" => {
+                let region = args.next().ok_or("--mem needs a value")?;
+                match parse_region(region) {
+                    Some((addr, len, None)) => regions.push((addr, len)),
+                    _ => return Err(format!("{} is not a valid memory region, it should look like ADDR:LEN", region)),
+                }
+            },
+            "--watch" => {
+                let region = args.next().ok_or("--watch needs a value")?;
+                let watchpoint = match parse_region(region) {
+                    Some((addr, len, None)) if len > 0 => Watchpoint::new(addr, len, WatchKind::Write),
+                    Some((addr, len, Some(kind))) if len > 0 => {
+                        let kind = WatchKind::from_name(kind).ok_or(format!("{} is not read, write or change", kind))?;
+                        Watchpoint::new(addr, len, kind)
+                    },
+                    _ => return Err(format!("{} is not a valid watchpoint, it should look like ADDR:LEN[:KIND]", region)),
+                };
+                watchpoints.push(watchpoint);
+            },
+            "--trace" => trace = true,
+            "--abi" => abi = true,
+            "--preset" => {
+                let name = args.next().ok_or("--preset needs a value")?;
+                config = CpuConfig::preset(name).ok_or(format!("{} is not a known preset", name))?;
+            },
+            "--syscalls" => {
+                let name = args.next().ok_or("--syscalls needs a value")?;
+                syscalls = Some(SyscallAbi::from_name(name).ok_or(format!("{} is not a known syscall abi", name))?);
+            },
+            "--sandbox" => sandbox = Some(PathBuf::from(args.next().ok_or("--sandbox needs a value")?)),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if file.is_none() => file = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    // the preset picks one too so this has to go on after
+    if let Some(syscalls) = syscalls {
+        config = config.syscalls(syscalls);
+    }
+
+    Ok(Options {
+        file: file.ok_or("no program given")?,
+        limit,
+        regions,
+        trace,
+        abi,
+        config,
+        sandbox,
+        watchpoints,
+    })
+}
+
+// returns the exit code for the process
+pub fn run(args: &[String]) -> i32 {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return EXIT_OK;
+    }
+
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    let mut cpu = match CPU::new(options.config) {
+        Ok(cpu) => cpu,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_USAGE;
+        }
+    };
+    cpu.set_sandbox(options.sandbox);
+    // nothing steps back here so theres no point keeping history
+    cpu.set_history_limit(0);
+    for watchpoint in options.watchpoints {
+        cpu.add_watchpoint(watchpoint);
+    }
+    let labels = match load(&mut cpu, &options.file) {
+        Ok(labels) => labels,
+        Err(code) => return code,
+    };
+
+    // stdin only gets read when the program checks for input, so programs that dont
+    // want any never wait on it
+    if let Some(uart) = cpu.view_uart() {
+        uart.set_input(|| {
+            let mut byte = [0];
+            matches!(io::stdin().read(&mut byte), Ok(1)).then_some(byte[0])
+        });
+    }
+
+    let mut console = Console::default();
+    let steps = if options.trace {
+        let labels = labels.into_iter().map(|(name, address)| (address, name)).collect();
+        trace(&mut cpu, options.limit, &Disassembler::new(options.abi, labels), &mut console)
+    } else {
+        let mut steps = 0;
+        while steps < options.limit && !cpu.is_halted() && cpu.view_watch_hit().is_none() {
+            steps += cpu.run((options.limit - steps).min(OUTPUT_CHUNK));
+            console.print(&cpu);
+        }
+        steps
+    };
+    console.end_line();
+
+    print_registers(&cpu, options.abi);
+    for (addr, len) in &options.regions {
+        print_memory(&cpu, *addr, *len);
+    }
+
+    if let Some(fault) = cpu.view_fault() {
+        eprintln!("fault after {} instructions at pc 0x{:x}: {}", steps, cpu.view_csrs().mepc, fault);
+        return EXIT_FAULT;
+    }
+    if let Some(hit) = cpu.view_watch_hit() {
+        eprintln!("watchpoint after {} instructions, {}", steps, hit);
+        return EXIT_WATCH;
+    }
+    if !cpu.is_halted() {
+        eprintln!("stopped after {} instructions without hitting ebreak (pc 0x{:x})", steps, cpu.get_pc());
+        return EXIT_LIMIT;
+    }
+    // the program said how it went so thats what the process gives back, cut down to
+    // the 8 bits a process gets like a real exit would be
+    if let Some(code) = cpu.view_exit_code() {
+        println!("exited with code {} after {} instructions (pc 0x{:x})", code as i32, steps, cpu.get_pc());
+        return match (code & 0xFF) as i32 {
+            EXIT_FAULT..=EXIT_USAGE => EXIT_RESERVED,
+            code => code,
+        };
+    }
+    println!("halted after {} instructions (pc 0x{:x})", steps, cpu.get_pc());
+    EXIT_OK
+}
+
+// elf executables get loaded as they are, anything else is assembled.
+// gives back the names of addresses for the trace, or the exit code if it couldnt be loaded
+fn load(cpu: &mut CPU, file: &str) -> Result<Vec<(String, u32)>, i32> {
+    let bytes = fs::read(file).unwrap_or_default();
+    if Elf::is_elf(&bytes) {
+        let elf = Elf::parse(&bytes).and_then(|elf| cpu.load_elf(&elf).map(|_| elf));
+        return match elf {
+            Ok(elf) => Ok(elf.symbols),
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                Err(EXIT_ASSEMBLE)
+            }
+        };
+    }
+
+    let mut assembler = match Assembler::open_file(file) {
+        Ok(assembler) => assembler,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            return Err(EXIT_ASSEMBLE);
+        }
+    };
+    assembler.set_base(cpu.view_config().reset_vector);
+    match assembler.assemble_bytes() {
+        Ok(program) => {
+            if let Err(e) = cpu.load_program_sized(&program, assembler.view_size()) {
+                eprintln!("{}: {}", file, e);
+                return Err(EXIT_ASSEMBLE);
+            }
+        },
+        Err(errors) => {
+            for e in errors {
+                eprintln!("{}: {}", file, e);
+            }
+            return Err(EXIT_ASSEMBLE);
+        }
+    }
+    Ok(assembler.view_labels())
+}
+
+// what the program sends on the uart goes straight to stdout
+#[derive(Default)]
+struct Console {
+    // the last thing printed didnt end with a newline
+    line_open: bool,
+}
+
+impl Console {
+    fn print(&mut self, cpu: &CPU) {
+        let Some(output) = cpu.view_uart().map(|uart| uart.take_output()) else {
+            return;
+        };
+        if let Some(last) = output.last() {
+            self.line_open = *last != b'\n';
+            let mut stdout = io::stdout();
+            stdout.write_all(&output).ok();
+            stdout.flush().ok();
+        }
+    }
+
+    // so whatever gets printed next starts on its own line
+    fn end_line(&mut self) {
+        if self.line_open {
+            println!();
+            self.line_open = false;
+        }
+    }
+}
+
+// same as cpu.run but printing each instruction before it goes
+fn trace(cpu: &mut CPU, limit: u64, disassembler: &Disassembler, console: &mut Console) -> u64 {
+    let mut steps = 0;
+    while steps < limit && !cpu.is_halted() && cpu.view_watch_hit().is_none() {
+        console.end_line();
+        let pc = cpu.get_pc();
+        match cpu.view_word(pc) {
+            Some(word) => println!("0x{:08x}: {:08x}  {}", pc, word, disassembler.disassemble(word, pc)),
+            None => println!("0x{:08x}: ????????", pc),
+        }
+        cpu.step();
+        console.print(cpu);
+        steps += 1;
+    }
+    steps
+}
+
+fn print_registers(cpu: &CPU, abi: bool) {
+    println!("pc   = 0x{:08x}", cpu.get_pc());
+    for (i, x) in cpu.view_registers().iter().enumerate() {
+        let name = if abi { abi_name(i as u8).to_string() } else { format!("x{}", i) };
+        println!("{:<4} = 0x{:08x} ({})", name, x, *x as i32);
+    }
+}
+
+// 16 bytes a line, lines with nothing mapped just get left off.
+// bytes that cant be shown (like device registers) are --
+fn print_memory(cpu: &CPU, addr: u32, len: u32) {
+    let end = addr as u64 + len as u64;
+    println!("memory 0x{:x}..0x{:x}:", addr, end);
+    for line in (addr as u64..end).step_by(16) {
+        let bytes = (line..(line + 16).min(end)).map(|a| cpu.view_byte(a as u32)).collect::<Vec<Option<u8>>>();
+        if bytes.iter().all(|b| b.is_none()) {
+            continue;
+        }
+        let bytes = bytes.iter().map(|b| b.map_or("--".to_string(), |b| format!("{:02x}", b))).collect::<Vec<String>>();
+        println!("0x{:08x}: {}", line, bytes.join(" "));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::cpu::MEM_START;
+use crate::syscall::SyscallAbi;
+use crate::uart::{UART_BASE, UART_SIZE};
+
+// the tiny layout everything started with, 512 bytes with programs halfway in
+const DEFAULT_RAM_SIZE: u32 = 0x200;
+
+// how big ram is and where it goes, where the cpu starts, where the uart is and which
+// ecalls get answered. built up like
+// CpuConfig::default().ram_size(0x10000).stack_pointer(0x10000)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuConfig {
+    pub ram_base: u32,
+    pub ram_size: u32,
+    // where pc starts, flat programs (like from the assembler) get loaded here too
+    pub reset_vector: u32,
+    // what sp (x2) starts as, usually the top of ram since the stack grows down
+    pub stack_pointer: u32,
+    // base address of the uart, None to leave it off
+    pub uart: Option<u32>,
+    pub syscalls: SyscallAbi,
+}
+
+impl Default for CpuConfig {
+    fn default() -> Self {
+        CpuConfig {
+            ram_base: 0,
+            ram_size: DEFAULT_RAM_SIZE,
+            reset_vector: MEM_START as u32,
+            stack_pointer: 0,
+            uart: Some(UART_BASE),
+            syscalls: SyscallAbi::default(),
+        }
+    }
+}
+
+impl CpuConfig {
+    pub fn ram_base(mut self, base: u32) -> Self {
+        self.ram_base = base;
+        self
+    }
+
+    pub fn ram_size(mut self, size: u32) -> Self {
+        self.ram_size = size;
+        self
+    }
+
+    pub fn reset_vector(mut self, address: u32) -> Self {
+        self.reset_vector = address;
+        self
+    }
+
+    pub fn stack_pointer(mut self, address: u32) -> Self {
+        self.stack_pointer = address;
+        self
+    }
+
+    pub fn uart(mut self, base: Option<u32>) -> Self {
+        self.uart = base;
+        self
+    }
+
+    pub fn syscalls(mut self, abi: SyscallAbi) -> Self {
+        self.syscalls = abi;
+        self
+    }
+
+    // one past the last byte of ram, can be 4GiB so its bigger than a u32
+    pub fn ram_end(&self) -> u64 {
+        self.ram_base as u64 + self.ram_size as u64
+    }
+
+    // catches layouts that cant work before a cpu gets made with one
+    pub fn check(&self) -> Result<(), String> {
+        if self.ram_size == 0 || !self.ram_size.is_multiple_of(4) {
+            return Err(format!("ram size 0x{:x} has to be a non zero multiple of 4", self.ram_size));
+        }
+        if !self.ram_base.is_multiple_of(4) || !self.reset_vector.is_multiple_of(4) {
+            return Err("ram base and reset vector have to be multiples of 4".to_string());
+        }
+        if self.ram_end() > 1 << 32 {
+            return Err(format!("ram at 0x{:x} with 0x{:x} bytes goes past the end of the address space", self.ram_base, self.ram_size));
+        }
+        if !(self.ram_base as u64..self.ram_end()).contains(&(self.reset_vector as u64)) {
+            return Err(format!("reset vector 0x{:x} is outside of ram", self.reset_vector));
+        }
+        if let Some(uart) = self.uart {
+            let end = uart as u64 + UART_SIZE as u64;
+            if end > 1 << 32 || (uart as u64) < self.ram_end() && end > self.ram_base as u64 {
+                return Err(format!("uart at 0x{:x} overlaps ram or goes past the end of the address space", uart));
+            }
+        }
+        Ok(())
+    }
+
+    // layouts to pick from in the gui and cli.
+    // elf is where riscv32-unknown-elf-gcc links to by default (and what newlib expects from ecall),
+    // virt is like qemu's virt machine
+    pub fn presets() -> [(&'static str, CpuConfig); 4] {
+        let mib = 1024 * 1024;
+        [
+            ("tiny", CpuConfig::default()),
+            ("small", CpuConfig::default().ram_size(0x10000).stack_pointer(0x10000)),
+            ("elf", CpuConfig::default().ram_size(16 * mib).reset_vector(0x10000).stack_pointer(16 * mib).syscalls(SyscallAbi::Linux)),
+            ("virt", CpuConfig::default().ram_base(0x8000_0000).ram_size(128 * mib).reset_vector(0x8000_0000).stack_pointer(0x8000_0000 + 128 * mib)),
+        ]
+    }
+
+    pub fn preset(name: &str) -> Option<CpuConfig> {
+        CpuConfig::presets().into_iter().find(|(n, _)| *n == name).map(|(_, config)| config)
+    }
+}
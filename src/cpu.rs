@@ -1,319 +1,602 @@
+use std::collections::BTreeSet;
 use std::fmt::Display;
-use crate::instruction::{BInstruction, IInstruction, InstructionType, RInstruction, SInstruction};
+use std::path::PathBuf;
+use crate::bus::{Bus, BusError, MemoryMap, Ram};
+use crate::instruction::Instruction;
+use crate::config::CpuConfig;
+use crate::elf::{Elf, ElfError};
+use crate::csr::{CsrFile, MSTATUS_MIE, MSTATUS_MPIE};
+use crate::history::{History, HISTORY_LIMIT};
+use crate::syscall::{Outcome, Syscalls};
+use crate::trap::Trap;
+use crate::uart::{Uart, UART_SIZE};
+use crate::watch::{WatchHit, WatchKind, Watchpoint};
 
 // idk why i picked this number but i liked it 
-const MEM_START: usize = 0x100;
-const MEM_SIZE: u32 = 0x200;
-
-// used to store info on the current instruction 
-#[derive(Debug)]
-pub struct InstructionInfo {
-    pub instr_type: Option<InstructionType>,
-    pub name: Option<String>,
-    pub rd: Option<u8>,
-    funct3: u8,
-    pub rs1: u8,
-    pub rs2: Option<u8>,
-    funct7: Option<u8>,
-    pub imm: Option<i16>
-}
-
-impl Default for InstructionInfo {
-    fn default() -> InstructionInfo {
-        InstructionInfo {
-            instr_type: None,
-            name: None,
-            rd: None,
-            funct3: 0,
-            rs1: 0,
-            rs2: None,
-            funct7: None,
-            imm: None,
-        }
-    }
-}
+// (its the default reset vector, and where the assembler puts code unless told otherwise)
+pub const MEM_START: usize = 0x100;
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct CPU {
     registers: [u32; 32],
-    memory: [u8; MEM_SIZE as usize],
+    // ram from the config plus whatever else gets attached
+    bus: MemoryMap,
+    // the same uart thats on the bus, to get what the program printed and give it input
+    uart: Option<Uart>,
+    config: CpuConfig,
     pc: u32,
     break_flag: bool,
-    instruction_info: InstructionInfo,
+    // the last instruction that ran (or tried to)
+    instruction: Option<Instruction>,
+    csrs: CsrFile,
+    // set when a trap stopped the cpu because there was no handler for it
+    fault: Option<Trap>,
+    syscalls: Syscalls,
+    // set when the program stopped itself with an exit ecall
+    exit_code: Option<u32>,
+    // addresses run stops at before running, these stay through a reset
+    breakpoints: BTreeSet<u32>,
+    // loads and stores run stops after, these stay too
+    watchpoints: Vec<Watchpoint>,
+    // set when the last step set one off
+    watch_hit: Option<WatchHit>,
+    // set when an ecall is waiting on input, the step didnt happen and gets tried again
+    waiting: bool,
+    // what the last steps changed so they can be undone
+    history: History,
 }
 
 impl CPU {
+    // a layout that doesnt make sense is an error rather than a cpu with bits missing
+    pub fn new(config: CpuConfig) -> Result<CPU, String> {
+        config.check()?;
+        let mut bus = MemoryMap::new();
+        bus.attach("ram", config.ram_base, config.ram_size, Box::new(Ram::new(config.ram_size)))?;
+        let uart = match config.uart {
+            Some(base) => {
+                let uart = Uart::new();
+                bus.attach("uart", base, UART_SIZE, Box::new(uart.clone()))?;
+                Some(uart)
+            },
+            None => None,
+        };
+        let mut cpu = CPU {
+            registers: [0; 32],
+            bus,
+            uart,
+            config,
+            pc: 0,
+            break_flag: false,
+            instruction: None,
+            csrs: CsrFile::default(),
+            fault: None,
+            syscalls: Syscalls::new(config.syscalls),
+            exit_code: None,
+            breakpoints: BTreeSet::new(),
+            watchpoints: vec![],
+            watch_hit: None,
+            waiting: false,
+            history: History::new(HISTORY_LIMIT),
+        };
+        cpu.reset();
+        Ok(cpu)
+    }
+
     pub fn get_pc(&self) -> u32 {
         self.pc
     }
 
-    pub fn view_instr_info(&self) -> &InstructionInfo {
-        &self.instruction_info
+    pub fn view_instruction(&self) -> Option<&Instruction> {
+        self.instruction.as_ref()
     }
     pub fn view_registers(&self) -> &[u32; 32] {
         &self.registers
     }
-    pub fn view_memory(&self) -> &[u8; MEM_SIZE as usize] {
-        &self.memory
+    pub fn view_uart(&self) -> Option<&Uart> {
+        self.uart.as_ref()
+    }
+    pub fn view_bus(&self) -> &MemoryMap {
+        &self.bus
+    }
+    // the byte at an address without going through the traps or setting off any devices,
+    // None if theres nothing there to show
+    pub fn view_byte(&self, address: u32) -> Option<u8> {
+        self.bus.peek(address)
+    }
+    pub fn view_config(&self) -> &CpuConfig {
+        &self.config
+    }
+    // the word at an address without going through the traps, for showing what's there
+    pub fn view_word(&self, address: u32) -> Option<u32> {
+        self.peek_value(address, 4)
+    }
+    pub fn view_csrs(&self) -> &CsrFile {
+        &self.csrs
+    }
+    pub fn view_fault(&self) -> Option<&Trap> {
+        self.fault.as_ref()
+    }
+    pub fn view_exit_code(&self) -> Option<u32> {
+        self.exit_code
+    }
+
+    pub fn view_breakpoints(&self) -> &BTreeSet<u32> {
+        &self.breakpoints
+    }
+    pub fn set_breakpoint(&mut self, address: u32) {
+        self.breakpoints.insert(address);
+    }
+    pub fn remove_breakpoint(&mut self, address: u32) {
+        self.breakpoints.remove(&address);
+    }
+    // the next instruction to run has a breakpoint on it
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.pc)
+    }
+
+    pub fn view_watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) {
+        self.watchpoints.retain(|w| w != watchpoint);
+    }
+    // what the last step set off, if anything
+    pub fn view_watch_hit(&self) -> Option<&WatchHit> {
+        self.watch_hit.as_ref()
+    }
+
+    // the only directory ecalls can open files in, None to not allow any
+    pub fn set_sandbox(&mut self, dir: Option<PathBuf>) {
+        self.syscalls.set_sandbox(dir);
     }
     
+    // puts a device (or rom, or more ram) on the bus, it cant overlap anything already there
+    pub fn attach(&mut self, name: &str, base: u32, size: u32, device: Box<dyn Bus>) -> Result<(), String> {
+        self.bus.attach(name, base, size, device)
+    }
+
     pub fn reset(&mut self) {
-        self.instruction_info = InstructionInfo::default();
+        self.instruction = None;
         self.registers = [0; 32];
-        for i in 0..MEM_START {
-            self.memory[i] = 0;
+        self.registers[2] = self.config.stack_pointer;
+        // everything below where the program goes, the program itself is left alone
+        let below = self.config.reset_vector.saturating_sub(self.config.ram_base).min(self.config.ram_size);
+        self.bus.load(self.config.ram_base, &vec![0; below as usize]).ok();
+        if let Some(uart) = &self.uart {
+            uart.reset();
         }
-        self.pc = MEM_START as u32;
+        self.pc = self.config.reset_vector;
         self.break_flag = false;
-    }
-
-    pub fn load_program(&mut self, program: &Vec<u8>) {
-        let mut offset = 0;
-        for word in program.iter() {
-            self.memory[MEM_START + offset] = *word;
-            offset += 1;
+        self.csrs = CsrFile::default();
+        self.fault = None;
+        self.syscalls.reset();
+        self.exit_code = None;
+        self.watch_hit = None;
+        self.history.clear();
+    }
+
+    // the image goes at the reset vector, nothing gets written unless all of it fits
+    pub fn load_program(&mut self, program: &[u8]) -> Result<(), String> {
+        let size = u32::try_from(program.len()).unwrap_or(u32::MAX);
+        self.load_program_sized(program, size)
+    }
+
+    // same as load_program but the program takes up size bytes, more than the image when the
+    // assembler left .space off the end. whatever is past the image gets zeroed
+    pub fn load_program_sized(&mut self, program: &[u8], size: u32) -> Result<(), String> {
+        let start = self.config.reset_vector;
+        let size = size.max(u32::try_from(program.len()).unwrap_or(u32::MAX));
+        if size > 0 && !self.bus.contains(start, size) {
+            return Err(format!("program at 0x{:x} ({} bytes) does not fit in memory", start, size));
+        }
+        self.history.clear();
+        self.syscalls.set_heap(start.wrapping_add(size));
+        self.bus.load(start, program).map_err(|e| e.to_string())?;
+        // a bit at a time so reserving a lot doesnt need a copy of it
+        let zeros = [0; 4096];
+        let mut at = program.len() as u32;
+        while at < size {
+            let n = (size - at).min(zeros.len() as u32);
+            self.bus.load(start.wrapping_add(at), &zeros[..n as usize]).map_err(|e| e.to_string())?;
+            at += n;
+        }
+        self.pc = start;
+        Ok(())
+    }
+
+    // puts every segment where the elf wants it and starts at its entry point.
+    // nothing gets written unless all of it fits
+    pub fn load_elf(&mut self, elf: &Elf) -> Result<(), ElfError> {
+        self.history.clear();
+        for segment in &elf.segments {
+            if segment.size > 0 && !self.bus.contains(segment.address, segment.size) {
+                return Err(ElfError::new(format!(
+                    "segment at 0x{:x} ({} bytes) does not fit in memory",
+                    segment.address, segment.size,
+                )));
+            }
         }
-        self.pc = MEM_START as u32;
+        for segment in elf.segments.iter().filter(|s| s.size > 0) {
+            // whatever isnt in the file is bss and starts as zero
+            let mut bytes = segment.data.clone();
+            bytes.resize(segment.size as usize, 0);
+            self.bus.load(segment.address, &bytes).map_err(|e| ElfError::new(e.to_string()))?;
+        }
+        // the heap goes after whichever segment ends last
+        let end = elf.segments.iter().map(|s| s.address as u64 + s.size as u64).max().unwrap_or(0);
+        self.syscalls.set_heap(end.min(u32::MAX as u64) as u32);
+        self.pc = elf.entry;
+        Ok(())
     }
     
-    pub fn run(&mut self) {
-        let mut result = self.step();
-        while result {
-            result = self.step();
+    // keep stepping until the cpu stops, gets to a breakpoint, sets off a watchpoint or we hit the limit,
+    // gives back how many steps ran. the first step always runs so starting on a
+    // breakpoint carries on from it
+    pub fn run(&mut self, limit: u64) -> u64 {
+        let mut steps = 0;
+        while steps < limit && self.step() {
+            steps += 1;
+            if self.at_breakpoint() || self.watch_hit.is_some() {
+                break;
+            }
         }
+        steps
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.break_flag
     }
 
     pub fn step(&mut self) -> bool {
-        if self.break_flag || self.pc > (MEM_SIZE - 0x4) {
+        if self.break_flag {
             return false
         }
-        self.instruction_info = InstructionInfo::default();
-        let instr: u32 = self.fetch();
-        self.decode(instr);
-        self.advance();
+        let registers = self.registers;
+        self.history.begin(self.pc, self.csrs, self.instruction, self.syscalls.brk());
+        self.instruction = None;
+        self.watch_hit = None;
+        self.waiting = false;
+        match self.fetch().and_then(|instr| self.execute(instr)) {
+            // nothing changed so theres nothing to undo or count, otherwise a program sat
+            // waiting on input fills the history up with steps that did nothing
+            Ok(()) if self.waiting => {
+                self.history.cancel();
+                return true;
+            },
+            Ok(()) => {
+                self.advance();
+                self.csrs.instret = self.csrs.instret.wrapping_add(1);
+            },
+            Err(trap) => self.trap(trap),
+        }
+        self.csrs.cycle = self.csrs.cycle.wrapping_add(1);
+        // x0 is hardwired to zero, easier to just clear it after than check every write
+        self.registers[0] = 0;
+        self.history.finish(&registers, &self.registers);
 
         true
     }
 
-    fn fetch(&self) -> u32 {
-        self.memory[self.pc as usize] as u32
-            | (self.memory[self.pc as usize + 1] as u32) << 8
-            | (self.memory[self.pc as usize + 2] as u32) << 16
-            | (self.memory[self.pc as usize + 3] as u32) << 24
+    // puts everything the last step changed back, false if theres nothing left to undo.
+    // a step that stopped the cpu can be undone too, which starts it going again
+    pub fn step_back(&mut self) -> bool {
+        let Some(delta) = self.history.pop() else {
+            return false;
+        };
+        for (address, byte) in delta.memory.iter().rev() {
+            self.bus.load(*address, &[*byte]).ok();
+        }
+        for (register, value) in delta.registers {
+            self.registers[register as usize] = value;
+        }
+        self.pc = delta.pc;
+        self.csrs = delta.csrs;
+        self.instruction = delta.instruction;
+        self.syscalls.restore_brk(delta.brk);
+        // a step only runs when none of these are set
+        self.break_flag = false;
+        self.fault = None;
+        self.exit_code = None;
+        self.watch_hit = None;
+        true
     }
 
-    fn get_word(&self, base: u32, offset: i32) -> u32 {
-        let start = (base as i32 + offset) as usize;
-        self.memory[start] as u32
-            | (self.memory[start + 1] as u32) << 8
-            | (self.memory[start + 2] as u32) << 16
-            | (self.memory[start + 3] as u32) << 24
+    // like run but backwards, until it gets back to a breakpoint or runs out of history.
+    // gives back how many steps got undone
+    pub fn reverse_continue(&mut self, limit: u64) -> u64 {
+        let mut steps = 0;
+        while steps < limit && self.step_back() {
+            steps += 1;
+            if self.at_breakpoint() {
+                break;
+            }
+        }
+        steps
     }
 
-    fn set_word(&mut self, word: u32, base: u32, offset: i32) {
-        let start = (base as i32 + offset) as usize;
-
-        self.memory[start] = (word & 0xFF) as u8 ;
-        self.memory[start+ 1] = (word >> 8 & 0xFF) as u8 ;
-        self.memory[start+ 2] = (word >> 16 & 0xFF) as u8 ;
-        self.memory[start + 3] = (word >> 24 & 0xFF) as u8 ;
+    // how many steps can be undone right now
+    pub fn view_history_len(&self) -> usize {
+        self.history.len()
     }
 
-    fn decode(&mut self, instruction: u32) {
-        match (instruction & 0x7F) as u8 {
-            0x33 => self.decode_r(instruction),
-            0x13 | 0x73 | 0x3 => self.decode_i(instruction),
-            0x63 => self.decode_b(instruction),
-            0x23 => self.decode_s(instruction),
-            _ => (),
-        }
+    // 0 stops keeping any, which is a bit faster when nothing is going to step back
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
     }
 
-    fn decode_r(&mut self, instruction: u32) {
-        let ins = RInstruction::new(instruction);
+    // exceptions save where they happened and jump to mtvec.
+    // if no handler has been set up (mtvec is 0) theres nowhere to go so just stop.
+    // ebreak stopping like that is how programs normally end so its not a fault
+    fn trap(&mut self, trap: Trap) {
+        self.csrs.mepc = self.pc;
+        self.csrs.mcause = trap.cause();
+        self.csrs.mtval = trap.tval();
+        // interrupts get turned off in the handler, remembering if they were on before
+        let mie = self.csrs.mstatus & MSTATUS_MIE;
+        self.csrs.mstatus = (self.csrs.mstatus & !(MSTATUS_MIE | MSTATUS_MPIE)) | (mie << 4);
 
-        if ins.opcode != 0x33 {
+        if self.csrs.mtvec == 0 {
+            self.break_flag = true;
+            if !matches!(trap, Trap::Breakpoint(_)) {
+                self.fault = Some(trap);
+            }
             return;
         }
+        // only direct mode, the low bits are the mode so ignore them
+        self.pc = self.csrs.mtvec & !0x3;
+    }
 
-        if ins.funct7 == 0x01  {
-            // multiply extension
-            return
-        }
-
-        self.instruction_info.instr_type = Some(InstructionType::RInstr);
-        self.instruction_info.rs1 = ins.rs1;
-        self.instruction_info.rs2 = Some(ins.rs2);
-        self.instruction_info.rd = Some(ins.rd);
-
-        match ins.funct3 {
-            0x0 => {
-                if ins.funct7 == 0x00 {
-                    self.instruction_info.name = Some("Add".to_string());
-                    self.add(ins.rd, ins.rs1, ins.rs2);
-                } else if ins.funct7 == 0x20 {
-                    self.instruction_info.name = Some("Sub".to_string());
-                    self.sub(ins.rd, ins.rs1, ins.rs2);
-                }
-            },
-            0x4 => {
-                self.instruction_info.name = Some("Xor".to_string());
-                self.xor(ins.rd, ins.rs1, ins.rs2);
-            },
-            0x6 => {
-                self.instruction_info.name = Some("Or".to_string());
-                self.or(ins.rd, ins.rs1, ins.rs2);
-            },
-            0x7 => {
-                self.instruction_info.name = Some("And".to_string());
-                self.and(ins.rd, ins.rs1, ins.rs2);
-            },
-            0x1 => {
-                self.instruction_info.name = Some("Shift Left Logical".to_string());
-                self.shift_left_logical(ins.rd, ins.rs1, ins.rs2);
-            },
-            0x5 => {
-                if ins.funct7 == 0x00 {
-                    self.instruction_info.name = Some("Shift Right Logical".to_string());
-                    self.shift_right_logical(ins.rd, ins.rs1, ins.rs2);
-                } else if ins.funct7 == 0x20 {
-                    self.instruction_info.name = Some("Shift Right Arithmetic".to_string());
-                    self.shift_right_arithmetic(ins.rd, ins.rs1, ins.rs2);
-                }
-            }
-            _ => {
-                self.instruction_info.name = Some("UNKNOWN R INSTRUCTION".to_string());
-                println!(
-                    "R {:b}\nRD: {:x} R1: {:x} R2: {:x} 3: {:x} 7: {:x} OPCODE: {:x}",
-                    instruction, ins.rd, ins.rs1, ins.rs2, ins.funct3, ins.funct7, ins.opcode
-                );
-            }
+    fn fetch(&mut self) -> Result<u32, Trap> {
+        if !self.pc.is_multiple_of(4) {
+            return Err(Trap::InstructionAddressMisaligned(self.pc));
         }
+        self.bus.read_word(self.pc).map_err(|e| Trap::InstructionAccessFault(e.address()))
     }
 
-    fn decode_i(&mut self, instruction: u32) {
-        let ins = IInstruction::new(instruction);
-        if ins.opcode == 0x73 && ins.imm == 1 {
-            self.break_flag = true;
-            return;
+    // work out the real address for a load/store and make sure its lined up,
+    // whether theres anything there is up to the bus
+    fn load_address(&self, base: u32, offset: i32, size: u32) -> Result<u32, Trap> {
+        let addr = base.wrapping_add(offset as u32);
+        if !addr.is_multiple_of(size) {
+            return Err(Trap::LoadAddressMisaligned(addr));
         }
+        Ok(addr)
+    }
 
-        if (ins.opcode != 0x13) && (ins.opcode != 0x3) {
-            return;
+    fn store_address(&self, base: u32, offset: i32, size: u32) -> Result<u32, Trap> {
+        let addr = base.wrapping_add(offset as u32);
+        if !addr.is_multiple_of(size) {
+            return Err(Trap::StoreAddressMisaligned(addr));
         }
+        Ok(addr)
+    }
 
-        self.instruction_info.instr_type = Some(InstructionType::IInstr);
-        self.instruction_info.rs1 = ins.rs1;
-        self.instruction_info.rd = Some(ins.rd);
-        self.instruction_info.imm = Some(ins.imm);
-
-        if ins.opcode == 0x3 {
-            match ins.funct3 {
-                0x2 => {
-                    self.instruction_info.name = Some("LW".to_string());
-                    self.load_word(ins.rd, ins.rs1, ins.imm as i32);
-                }
-                _ => {
-                    self.instruction_info.name = Some("Unknown I".to_string());
-                }
-            }
-        } else {
-            match ins.funct3 {
-                0x0 => {
-                    self.instruction_info.name = Some("AddI".to_string());
-                    self.addimm(ins.rd, ins.rs1, ins.imm as i32);
-                },
-                0x4 => {
-                    self.instruction_info.name = Some("XorI".to_string());
-                    self.xorimm(ins.rd, ins.rs1, ins.imm as i32);
-                },
-                0x6 => {
-                    self.instruction_info.name = Some("OrI".to_string());
-                    self.orimm(ins.rd, ins.rs1, ins.imm as i32);
-                },
-                0x7 => {
-                    self.instruction_info.name = Some("AndI".to_string());
-                    self.andimm(ins.rd, ins.rs1, ins.imm as i32);
-                },
-                _ => {
-                    self.instruction_info.name = Some("Unknown I".to_string());
-                }
-            }
-        }
+    fn load_fault(e: BusError) -> Trap {
+        Trap::LoadAccessFault(e.address())
+    }
 
+    fn store_fault(e: BusError) -> Trap {
+        Trap::StoreAccessFault(e.address())
+    }
 
+    // little endian value of size bytes, None if any of them cant be looked at
+    fn peek_value(&self, address: u32, size: u32) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..size {
+            value |= (self.bus.peek(address.checked_add(i)?)? as u32) << (i * 8);
+        }
+        Some(value)
     }
 
-    fn decode_b(&mut self, instruction: u32) {
-        let ins = BInstruction::new(instruction);
+    // what was there before a store, only bothered with if something is watching
+    fn value_before(&self, address: u32, size: u32) -> u32 {
+        if self.watchpoints.is_empty() {
+            return 0;
+        }
+        self.peek_value(address, size).unwrap_or(0)
+    }
 
-        if ins.opcode != 0x63 {
+    // remembers the first watchpoint the access sets off, reads have the same old and new.
+    // an ecall can write lots of bytes in one go, only the first one that sets one off is kept
+    fn watch(&mut self, write: bool, address: u32, size: u32, old: u32, new: u32) {
+        if self.watch_hit.is_some() {
             return;
         }
+        let hit = self.watchpoints.iter().find(|w| w.covers(address, size) && match w.kind {
+            WatchKind::Read => !write,
+            WatchKind::Write => write,
+            WatchKind::Change => write && w.changed(address, size, old, new),
+        });
+        if let Some(watchpoint) = hit {
+            self.watch_hit = Some(WatchHit { watchpoint: *watchpoint, pc: self.pc, address, size, old, new });
+        }
+    }
 
-        self.instruction_info.instr_type = Some(InstructionType::BInstr);
-        self.instruction_info.rs1 = ins.rs1;
-        self.instruction_info.rs2 = Some(ins.rs2);
-        self.instruction_info.imm = Some(ins.imm);
-
-        match ins.funct3 {
-            0x0 => {
-                self.instruction_info.name = Some("BEQ".to_string());
-                self.brancheq(ins.rs1, ins.rs2, ins.imm as i32)
+    fn get_word(&mut self, base: u32, offset: i32) -> Result<u32, Trap> {
+        let addr = self.load_address(base, offset, 4)?;
+        let word = self.bus.read_word(addr).map_err(CPU::load_fault)?;
+        self.watch(false, addr, 4, word, word);
+        Ok(word)
+    }
+
+    fn get_half(&mut self, base: u32, offset: i32) -> Result<u16, Trap> {
+        let addr = self.load_address(base, offset, 2)?;
+        let half = self.bus.read_half(addr).map_err(CPU::load_fault)?;
+        self.watch(false, addr, 2, half as u32, half as u32);
+        Ok(half)
+    }
+
+    fn get_byte(&mut self, base: u32, offset: i32) -> Result<u8, Trap> {
+        let addr = self.load_address(base, offset, 1)?;
+        let byte = self.bus.read_byte(addr).map_err(CPU::load_fault)?;
+        self.watch(false, addr, 1, byte as u32, byte as u32);
+        Ok(byte)
+    }
+
+    fn set_half(&mut self, half: u16, base: u32, offset: i32) -> Result<(), Trap> {
+        let addr = self.store_address(base, offset, 2)?;
+        let old = self.value_before(addr, 2);
+        self.history.save_memory(&self.bus, addr, 2);
+        self.bus.write_half(addr, half).map_err(CPU::store_fault)?;
+        self.watch(true, addr, 2, old, half as u32);
+        Ok(())
+    }
+
+    fn set_byte(&mut self, byte: u8, base: u32, offset: i32) -> Result<(), Trap> {
+        let addr = self.store_address(base, offset, 1)?;
+        let old = self.value_before(addr, 1);
+        self.history.save_memory(&self.bus, addr, 1);
+        self.bus.write_byte(addr, byte).map_err(CPU::store_fault)?;
+        self.watch(true, addr, 1, old, byte as u32);
+        Ok(())
+    }
+
+    fn set_word(&mut self, word: u32, base: u32, offset: i32) -> Result<(), Trap> {
+        let addr = self.store_address(base, offset, 4)?;
+        let old = self.value_before(addr, 4);
+        self.history.save_memory(&self.bus, addr, 4);
+        self.bus.write_word(addr, word).map_err(CPU::store_fault)?;
+        self.watch(true, addr, 4, old, word);
+        Ok(())
+    }
+
+    // works out what the word is and runs it, anything that doesnt decode is illegal
+    fn execute(&mut self, word: u32) -> Result<(), Trap> {
+        use Instruction::*;
+        let instruction = Instruction::decode(word).ok_or(Trap::IllegalInstruction(word))?;
+        self.instruction = Some(instruction);
+
+        match instruction {
+            Add { rd, rs1, rs2 } => self.add(rd, rs1, rs2),
+            Sub { rd, rs1, rs2 } => self.sub(rd, rs1, rs2),
+            Sll { rd, rs1, rs2 } => self.shift_left_logical(rd, rs1, rs2),
+            Slt { rd, rs1, rs2 } => self.set_less_than(rd, rs1, rs2),
+            Sltu { rd, rs1, rs2 } => self.set_less_than_unsigned(rd, rs1, rs2),
+            Xor { rd, rs1, rs2 } => self.xor(rd, rs1, rs2),
+            Srl { rd, rs1, rs2 } => self.shift_right_logical(rd, rs1, rs2),
+            Sra { rd, rs1, rs2 } => self.shift_right_arithmetic(rd, rs1, rs2),
+            Or { rd, rs1, rs2 } => self.or(rd, rs1, rs2),
+            And { rd, rs1, rs2 } => self.and(rd, rs1, rs2),
+            Mul { rd, rs1, rs2 } => self.mul(rd, rs1, rs2),
+            Mulh { rd, rs1, rs2 } => self.mul_high(rd, rs1, rs2),
+            Mulhsu { rd, rs1, rs2 } => self.mul_high_signed_unsigned(rd, rs1, rs2),
+            Mulhu { rd, rs1, rs2 } => self.mul_high_unsigned(rd, rs1, rs2),
+            Div { rd, rs1, rs2 } => self.div(rd, rs1, rs2),
+            Divu { rd, rs1, rs2 } => self.div_unsigned(rd, rs1, rs2),
+            Rem { rd, rs1, rs2 } => self.rem(rd, rs1, rs2),
+            Remu { rd, rs1, rs2 } => self.rem_unsigned(rd, rs1, rs2),
+            Addi { rd, rs1, imm } => self.addimm(rd, rs1, imm),
+            Slti { rd, rs1, imm } => self.set_less_than_imm(rd, rs1, imm),
+            Sltiu { rd, rs1, imm } => self.set_less_than_imm_unsigned(rd, rs1, imm),
+            Xori { rd, rs1, imm } => self.xorimm(rd, rs1, imm),
+            Ori { rd, rs1, imm } => self.orimm(rd, rs1, imm),
+            Andi { rd, rs1, imm } => self.andimm(rd, rs1, imm),
+            Slli { rd, rs1, shamt } => self.shift_left_logical_imm(rd, rs1, shamt),
+            Srli { rd, rs1, shamt } => self.shift_right_logical_imm(rd, rs1, shamt),
+            Srai { rd, rs1, shamt } => self.shift_right_arithmetic_imm(rd, rs1, shamt),
+            Lb { rd, rs1, imm } => self.load_byte(rd, rs1, imm)?,
+            Lh { rd, rs1, imm } => self.load_half(rd, rs1, imm)?,
+            Lw { rd, rs1, imm } => self.load_word(rd, rs1, imm)?,
+            Lbu { rd, rs1, imm } => self.load_byte_unsigned(rd, rs1, imm)?,
+            Lhu { rd, rs1, imm } => self.load_half_unsigned(rd, rs1, imm)?,
+            Jalr { rd, rs1, imm } => self.jump_and_link_reg(rd, rs1, imm)?,
+            Sb { rs1, rs2, imm } => self.store_byte(rs1, rs2, imm)?,
+            Sh { rs1, rs2, imm } => self.store_half(rs1, rs2, imm)?,
+            Sw { rs1, rs2, imm } => self.store_word(rs1, rs2, imm)?,
+            Beq { rs1, rs2, imm } => self.brancheq(rs1, rs2, imm)?,
+            Bne { rs1, rs2, imm } => self.branchneq(rs1, rs2, imm)?,
+            Blt { rs1, rs2, imm } => self.branchlt(rs1, rs2, imm)?,
+            Bge { rs1, rs2, imm } => self.branchge(rs1, rs2, imm)?,
+            Bltu { rs1, rs2, imm } => self.branchltu(rs1, rs2, imm)?,
+            Bgeu { rs1, rs2, imm } => self.branchgeu(rs1, rs2, imm)?,
+            Lui { rd, imm } => self.load_upper_imm(rd, imm),
+            Auipc { rd, imm } => self.add_upper_imm_pc(rd, imm),
+            Jal { rd, imm } => self.jump_and_link(rd, imm)?,
+            // rw only reads if rd isnt x0, rs and rc only write if the source isnt x0 so
+            // reading a read only csr with them is fine
+            Csrrw { rd, rs1, csr } => {
+                let src = self.registers[rs1 as usize];
+                self.csr_access(word, rd, csr, rd != 0, |_| Some(src))?
             },
-            0x1 => {
-                self.instruction_info.name = Some("BNE".to_string());
-                self.branchneq(ins.rs1, ins.rs2, ins.imm as i32)
+            Csrrs { rd, rs1, csr } => {
+                let src = self.registers[rs1 as usize];
+                self.csr_access(word, rd, csr, true, |old| (rs1 != 0).then_some(old | src))?
             },
-            0x4 => {
-                self.instruction_info.name = Some("BLT".to_string());
-                self.branchlt(ins.rs1, ins.rs2, ins.imm as i32)
+            Csrrc { rd, rs1, csr } => {
+                let src = self.registers[rs1 as usize];
+                self.csr_access(word, rd, csr, true, |old| (rs1 != 0).then_some(old & !src))?
             },
-            0x5 => {
-                self.instruction_info.name = Some("BGE".to_string());
-                self.branchge(ins.rs1, ins.rs2, ins.imm as i32)
-            },
-            _ => println!("UNKNOWN B {:b}", instruction),
+            // the i versions use the rs1 bits as a 5 bit unsigned value instead
+            Csrrwi { rd, uimm, csr } => self.csr_access(word, rd, csr, rd != 0, |_| Some(uimm as u32))?,
+            Csrrsi { rd, uimm, csr } => self.csr_access(word, rd, csr, true, |old| (uimm != 0).then_some(old | uimm as u32))?,
+            Csrrci { rd, uimm, csr } => self.csr_access(word, rd, csr, true, |old| (uimm != 0).then_some(old & !(uimm as u32)))?,
+            Ecall => self.environment_call()?,
+            Ebreak => return Err(Trap::Breakpoint(self.pc)),
+            Mret => self.trap_return(),
         }
+        Ok(())
     }
 
-    fn decode_s(&mut self, instruction: u32) {
-        let ins = SInstruction::new(instruction);
-
-        if ins.opcode != 0x23 {
-            return;
+    // new gets the old value and gives back what to write, if anything.
+    // csrs that dont exist (or writing a read only one) are illegal
+    fn csr_access(&mut self, word: u32, rd: u8, csr: u16, read: bool, new: impl Fn(u32) -> Option<u32>) -> Result<(), Trap> {
+        let illegal = Trap::IllegalInstruction(word);
+        let old = if read {
+            self.csrs.read(csr).ok_or(illegal)?
+        } else {
+            0
+        };
+        if let Some(new) = new(old) {
+            self.csrs.write(csr, new).ok_or(illegal)?;
         }
-
-        self.instruction_info.instr_type = Some(InstructionType::SInstr);
-        self.instruction_info.rs1 = ins.rs1;
-        self.instruction_info.rs2 = Some(ins.rs2);
-        self.instruction_info.imm = Some(ins.imm);
-
-        match ins.funct3 {
-            0x2 => {
-                self.instruction_info.name = Some("SW".to_string());
-                self.store_word(ins.rs1, ins.rs2, ins.imm as i32);
-            }
-            _ => {
-                self.instruction_info.name = Some("Unknown S".to_string());
+        self.registers[rd as usize] = old;
+        Ok(())
+    }
+
+    // the syscall abi gets to answer it even with a trap handler set up,
+    // anything it doesnt know about still traps
+    fn environment_call(&mut self) -> Result<(), Trap> {
+        let mut written = vec![];
+        let outcome = self.syscalls.handle(&mut self.registers, &mut self.bus, self.uart.as_ref(), &mut written);
+        // whatever it wrote into memory counts the same as the program storing it
+        for (address, old) in written {
+            if let Some(old) = old {
+                self.history.save_byte(&self.bus, address, old);
             }
+            let new = self.bus.peek(address).unwrap_or(0);
+            self.watch(true, address, 1, old.unwrap_or(0) as u32, new as u32);
         }
-
+        match outcome? {
+            Outcome::Done => (),
+            // stays on the ecall to run it again next step
+            Outcome::Wait => self.waiting = true,
+            // stays on the ecall, like ebreak does
+            Outcome::Exit(code) => {
+                self.exit_code = Some(code);
+                self.break_flag = true;
+                self.pc = self.pc.wrapping_sub(4);
+            },
+        }
+        Ok(())
     }
 
     fn advance(&mut self) {
-        self.pc += 4;
+        self.pc = self.pc.wrapping_add(4);
     }
 
     // ARITHMETIC
     
     #[inline(always)]
     fn add(&mut self, rd: u8, r1: u8, r2: u8) {
-        self.registers[rd as usize] = self.registers[r1 as usize] + self.registers[r2 as usize];
+        self.registers[rd as usize] = self.registers[r1 as usize].wrapping_add(self.registers[r2 as usize]);
     }
 
     #[inline(always)]
     fn sub(&mut self, rd: u8, r1: u8, r2: u8) {
-        self.registers[rd as usize] = (self.registers[r1 as usize] as i32 - self.registers[r2 as usize] as i32) as u32;
+        self.registers[rd as usize] = self.registers[r1 as usize].wrapping_sub(self.registers[r2 as usize]);
     }
 
     #[inline(always)]
@@ -331,26 +614,134 @@ impl CPU {
         self.registers[rd as usize] = self.registers[r1 as usize] & self.registers[r2 as usize];
     }
 
+    // only the low 5 bits of r2 count as the shift amount
     #[inline(always)]
     fn shift_left_logical(&mut self, rd: u8, r1: u8, r2: u8) {
-        self.registers[rd as usize] = (self.registers[r1 as usize] ) << self.registers[r2 as usize];
+        self.registers[rd as usize] = (self.registers[r1 as usize] ) << (self.registers[r2 as usize] & 0x1F);
     }
 
     #[inline(always)]
     fn shift_right_logical(&mut self, rd: u8, r1: u8, r2: u8) {
-        self.registers[rd as usize] = (self.registers[r1 as usize]) >> self.registers[r2 as usize];
+        self.registers[rd as usize] = (self.registers[r1 as usize]) >> (self.registers[r2 as usize] & 0x1F);
     }
     
     // keeps sign
     #[inline(always)]
     fn shift_right_arithmetic(&mut self, rd: u8, r1: u8, r2: u8) {
-        self.registers[rd as usize] = (self.registers[r1 as usize]  as i32 >> self.registers[r2 as usize] as i32) as u32;
+        self.registers[rd as usize] = (self.registers[r1 as usize]  as i32 >> (self.registers[r2 as usize] & 0x1F)) as u32;
+    }
+
+    // shamt is already just 5 bits from decoding
+
+    #[inline(always)]
+    fn shift_left_logical_imm(&mut self, rd: u8, r1: u8, shamt: u8) {
+        self.registers[rd as usize] = self.registers[r1 as usize] << shamt;
+    }
+
+    #[inline(always)]
+    fn shift_right_logical_imm(&mut self, rd: u8, r1: u8, shamt: u8) {
+        self.registers[rd as usize] = self.registers[r1 as usize] >> shamt;
+    }
+
+    #[inline(always)]
+    fn shift_right_arithmetic_imm(&mut self, rd: u8, r1: u8, shamt: u8) {
+        self.registers[rd as usize] = (self.registers[r1 as usize] as i32 >> shamt) as u32;
+    }
+
+    // MULTIPLY / DIVIDE
+    // the high versions give the top 32 bits of the 64 bit product
+
+    #[inline(always)]
+    fn mul(&mut self, rd: u8, r1: u8, r2: u8) {
+        self.registers[rd as usize] = self.registers[r1 as usize].wrapping_mul(self.registers[r2 as usize]);
+    }
+
+    #[inline(always)]
+    fn mul_high(&mut self, rd: u8, r1: u8, r2: u8) {
+        let product = self.registers[r1 as usize] as i32 as i64 * self.registers[r2 as usize] as i32 as i64;
+        self.registers[rd as usize] = (product >> 32) as u32;
+    }
+
+    // r1 is signed, r2 is unsigned
+    #[inline(always)]
+    fn mul_high_signed_unsigned(&mut self, rd: u8, r1: u8, r2: u8) {
+        let product = self.registers[r1 as usize] as i32 as i64 * self.registers[r2 as usize] as i64;
+        self.registers[rd as usize] = (product >> 32) as u32;
+    }
+
+    #[inline(always)]
+    fn mul_high_unsigned(&mut self, rd: u8, r1: u8, r2: u8) {
+        let product = self.registers[r1 as usize] as u64 * self.registers[r2 as usize] as u64;
+        self.registers[rd as usize] = (product >> 32) as u32;
+    }
+
+    // dividing by zero doesnt trap, it gives -1 (all ones) and rem gives back r1.
+    // MIN / -1 overflows so it gives MIN with a remainder of 0
+
+    #[inline(always)]
+    fn div(&mut self, rd: u8, r1: u8, r2: u8) {
+        let dividend = self.registers[r1 as usize] as i32;
+        let divisor = self.registers[r2 as usize] as i32;
+        self.registers[rd as usize] = if divisor == 0 {
+            u32::MAX
+        } else {
+            dividend.wrapping_div(divisor) as u32
+        };
+    }
+
+    #[inline(always)]
+    fn div_unsigned(&mut self, rd: u8, r1: u8, r2: u8) {
+        let dividend = self.registers[r1 as usize];
+        let divisor = self.registers[r2 as usize];
+        self.registers[rd as usize] = dividend.checked_div(divisor).unwrap_or(u32::MAX);
+    }
+
+    #[inline(always)]
+    fn rem(&mut self, rd: u8, r1: u8, r2: u8) {
+        let dividend = self.registers[r1 as usize] as i32;
+        let divisor = self.registers[r2 as usize] as i32;
+        self.registers[rd as usize] = if divisor == 0 {
+            dividend as u32
+        } else {
+            dividend.wrapping_rem(divisor) as u32
+        };
+    }
+
+    #[inline(always)]
+    fn rem_unsigned(&mut self, rd: u8, r1: u8, r2: u8) {
+        let dividend = self.registers[r1 as usize];
+        let divisor = self.registers[r2 as usize];
+        self.registers[rd as usize] = dividend.checked_rem(divisor).unwrap_or(dividend);
+    }
+
+    // COMPARISONS
+    // rd gets 1 if r1 < r2 else 0
+
+    #[inline(always)]
+    fn set_less_than(&mut self, rd: u8, r1: u8, r2: u8) {
+        self.registers[rd as usize] = ((self.registers[r1 as usize] as i32) < (self.registers[r2 as usize] as i32)) as u32;
+    }
+
+    #[inline(always)]
+    fn set_less_than_unsigned(&mut self, rd: u8, r1: u8, r2: u8) {
+        self.registers[rd as usize] = (self.registers[r1 as usize] < self.registers[r2 as usize]) as u32;
+    }
+
+    #[inline(always)]
+    fn set_less_than_imm(&mut self, rd: u8, r1: u8, imm: i32) {
+        self.registers[rd as usize] = ((self.registers[r1 as usize] as i32) < imm) as u32;
+    }
+
+    // imm is still sign extended first, then compared as unsigned
+    #[inline(always)]
+    fn set_less_than_imm_unsigned(&mut self, rd: u8, r1: u8, imm: i32) {
+        self.registers[rd as usize] = (self.registers[r1 as usize] < imm as u32) as u32;
     }
 
     // rd = r1 + imm (u32?)
     #[inline(always)]
     fn addimm(&mut self, rd: u8, r1: u8, imm: i32) {
-        self.registers[rd as usize] = (self.registers[r1 as usize] as i32 + imm) as u32;
+        self.registers[rd as usize] = (self.registers[r1 as usize] as i32).wrapping_add(imm) as u32;
     }
 
     #[inline(always)]
@@ -370,75 +761,170 @@ impl CPU {
 
     // BRANCHING
     #[inline(always)]
-    fn branch(&mut self, imm: i32) {
-        if self.pc as i32 + imm - 4  >= 0 {
-            self.pc = (self.pc as i32 + imm  - 4) as u32;
+    fn branch(&mut self, imm: i32) -> Result<(), Trap> {
+        let target = self.pc.wrapping_add(imm as u32);
+        if !target.is_multiple_of(4) {
+            return Err(Trap::InstructionAddressMisaligned(target));
         }
+        // pc gets moved forward after so take 4 off the target
+        self.pc = target.wrapping_sub(4);
+        Ok(())
     }
 
     #[inline(always)]
-    fn brancheq(&mut self, r1: u8, r2: u8, imm: i32) {
-        if imm % 4 != 0 {
-            return;
-        }
+    fn brancheq(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
         if self.registers[r1 as usize] == self.registers[r2 as usize] {
-            self.branch(imm);
+            return self.branch(imm);
         }
+        Ok(())
     }
 
     #[inline(always)]
-    fn branchneq(&mut self, r1: u8, r2: u8, imm: i32) {
-        if imm % 4 != 0 {
-            return;
-        }
+    fn branchneq(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
         if self.registers[r1 as usize] != self.registers[r2 as usize] {
-            self.branch(imm);
+            return self.branch(imm);
         }
+        Ok(())
     }
 
     #[inline(always)]
-    fn branchlt(&mut self, r1: u8, r2: u8, imm: i32) {
-        if imm % 4 != 0 {
-            return;
+    fn branchlt(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
+        if (self.registers[r1 as usize] as i32) < (self.registers[r2 as usize] as i32) {
+            return self.branch(imm);
         }
-        if self.registers[r1 as usize] < self.registers[r2 as usize] {
-            self.branch(imm);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn branchge(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
+        if (self.registers[r1 as usize] as i32) >= (self.registers[r2 as usize] as i32) {
+            return self.branch(imm);
         }
+        Ok(())
     }
 
     #[inline(always)]
-    fn branchge(&mut self, r1: u8, r2: u8, imm: i32) {
-        if imm % 4 != 0 {
-            return;
+    fn branchltu(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
+        if self.registers[r1 as usize] < self.registers[r2 as usize] {
+            return self.branch(imm);
         }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn branchgeu(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
         if self.registers[r1 as usize] >= self.registers[r2 as usize] {
-            self.branch(imm);
+            return self.branch(imm);
+        }
+        Ok(())
+    }
+
+    // JUMPS
+    // same as branching, pc gets moved forward after so take 4 off the target.
+    // a bad target traps before rd gets written
+
+    #[inline(always)]
+    fn jump_and_link(&mut self, rd: u8, imm: i32) -> Result<(), Trap> {
+        let target = (self.pc as i32).wrapping_add(imm) as u32;
+        if !target.is_multiple_of(4) {
+            return Err(Trap::InstructionAddressMisaligned(target));
+        }
+        self.registers[rd as usize] = self.pc.wrapping_add(4);
+        self.pc = target.wrapping_sub(4);
+        Ok(())
+    }
+
+    // target has to be worked out before writing rd in case rd == r1
+    #[inline(always)]
+    fn jump_and_link_reg(&mut self, rd: u8, r1: u8, imm: i32) -> Result<(), Trap> {
+        let target = (self.registers[r1 as usize] as i32).wrapping_add(imm) as u32 & !1;
+        if !target.is_multiple_of(4) {
+            return Err(Trap::InstructionAddressMisaligned(target));
         }
+        self.registers[rd as usize] = self.pc.wrapping_add(4);
+        self.pc = target.wrapping_sub(4);
+        Ok(())
+    }
+
+    // go back to where the trap happened, the handler has to move mepc on itself
+    // if it wants to skip the instruction
+    #[inline(always)]
+    fn trap_return(&mut self) {
+        let mpie = self.csrs.mstatus & MSTATUS_MPIE;
+        self.csrs.mstatus = (self.csrs.mstatus & !MSTATUS_MIE) | (mpie >> 4) | MSTATUS_MPIE;
+        self.pc = self.csrs.mepc.wrapping_sub(4);
+    }
+
+    // UPPER IMMEDIATES
+    // imm already has the low 12 bits cleared
+
+    #[inline(always)]
+    fn load_upper_imm(&mut self, rd: u8, imm: i32) {
+        self.registers[rd as usize] = imm as u32;
+    }
+
+    #[inline(always)]
+    fn add_upper_imm_pc(&mut self, rd: u8, imm: i32) {
+        self.registers[rd as usize] = self.pc.wrapping_add(imm as u32);
     }
-        // MEMORY
 
-    fn load_word(&mut self, rd: u8, r1: u8, imm: i32) {
-        let word = self.get_word(self.registers[r1 as usize], imm);
+    // MEMORY
+
+    fn load_word(&mut self, rd: u8, r1: u8, imm: i32) -> Result<(), Trap> {
+        let word = self.get_word(self.registers[r1 as usize], imm)?;
         self.registers[rd as usize] = word;
+        Ok(())
+    }
+
+    // signed loads get sign extended up to 32 bits, the u versions just get zeros
+
+    fn load_half(&mut self, rd: u8, r1: u8, imm: i32) -> Result<(), Trap> {
+        let half = self.get_half(self.registers[r1 as usize], imm)?;
+        self.registers[rd as usize] = half as i16 as i32 as u32;
+        Ok(())
+    }
+
+    fn load_half_unsigned(&mut self, rd: u8, r1: u8, imm: i32) -> Result<(), Trap> {
+        let half = self.get_half(self.registers[r1 as usize], imm)?;
+        self.registers[rd as usize] = half as u32;
+        Ok(())
     }
 
-    fn store_word(&mut self, r1: u8, r2: u8, imm: i32) {
+    fn load_byte(&mut self, rd: u8, r1: u8, imm: i32) -> Result<(), Trap> {
+        let byte = self.get_byte(self.registers[r1 as usize], imm)?;
+        self.registers[rd as usize] = byte as i8 as i32 as u32;
+        Ok(())
+    }
+
+    fn load_byte_unsigned(&mut self, rd: u8, r1: u8, imm: i32) -> Result<(), Trap> {
+        let byte = self.get_byte(self.registers[r1 as usize], imm)?;
+        self.registers[rd as usize] = byte as u32;
+        Ok(())
+    }
+
+    fn store_word(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
         let word = self.registers[r2 as usize];
-        self.set_word(word, self.registers[r1 as usize], imm);
+        self.set_word(word, self.registers[r1 as usize], imm)
+    }
+
+    // stores only take the low bits of the register
 
+    fn store_half(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
+        let half = (self.registers[r2 as usize] & 0xFFFF) as u16;
+        self.set_half(half, self.registers[r1 as usize], imm)
+    }
+
+    fn store_byte(&mut self, r1: u8, r2: u8, imm: i32) -> Result<(), Trap> {
+        let byte = (self.registers[r2 as usize] & 0xFF) as u8;
+        self.set_byte(byte, self.registers[r1 as usize], imm)
     }
 
 }
 
+// the default layout always checks out
 impl Default for CPU {
     fn default() -> CPU {
-        CPU {
-            registers: [0; 32],
-            memory: [0; MEM_SIZE as usize],
-            pc: 0,
-            break_flag: false,
-            instruction_info: InstructionInfo::default(),
-        }
+        CPU::new(CpuConfig::default()).unwrap()
     }
 }
 
@@ -0,0 +1,135 @@
+// control and status registers. theres 4096 possible addresses but we only need a few so
+// just keep the ones we actually implement as fields and map the address onto them
+
+pub const MSTATUS: u16 = 0x300;
+pub const MISA: u16 = 0x301;
+pub const MIE: u16 = 0x304;
+pub const MTVEC: u16 = 0x305;
+pub const MSCRATCH: u16 = 0x340;
+pub const MEPC: u16 = 0x341;
+pub const MCAUSE: u16 = 0x342;
+pub const MTVAL: u16 = 0x343;
+pub const MIP: u16 = 0x344;
+pub const MCYCLE: u16 = 0xB00;
+pub const MINSTRET: u16 = 0xB02;
+pub const MCYCLEH: u16 = 0xB80;
+pub const MINSTRETH: u16 = 0xB82;
+pub const CYCLE: u16 = 0xC00;
+pub const TIME: u16 = 0xC01;
+pub const INSTRET: u16 = 0xC02;
+pub const CYCLEH: u16 = 0xC80;
+pub const TIMEH: u16 = 0xC81;
+pub const INSTRETH: u16 = 0xC82;
+pub const MVENDORID: u16 = 0xF11;
+pub const MARCHID: u16 = 0xF12;
+pub const MIMPID: u16 = 0xF13;
+pub const MHARTID: u16 = 0xF14;
+
+// names the assembler accepts (and anything printing csrs can use)
+pub const CSR_NAMES: [(&str, u16); 23] = [
+    ("mstatus", MSTATUS),
+    ("misa", MISA),
+    ("mie", MIE),
+    ("mtvec", MTVEC),
+    ("mscratch", MSCRATCH),
+    ("mepc", MEPC),
+    ("mcause", MCAUSE),
+    ("mtval", MTVAL),
+    ("mip", MIP),
+    ("mcycle", MCYCLE),
+    ("minstret", MINSTRET),
+    ("mcycleh", MCYCLEH),
+    ("minstreth", MINSTRETH),
+    ("cycle", CYCLE),
+    ("time", TIME),
+    ("instret", INSTRET),
+    ("cycleh", CYCLEH),
+    ("timeh", TIMEH),
+    ("instreth", INSTRETH),
+    ("mvendorid", MVENDORID),
+    ("marchid", MARCHID),
+    ("mimpid", MIMPID),
+    ("mhartid", MHARTID),
+];
+
+pub fn csr_address(name: &str) -> Option<u16> {
+    CSR_NAMES.iter().find(|(n, _)| *n == name).map(|(_, a)| *a)
+}
+
+pub fn csr_name(address: u16) -> Option<&'static str> {
+    CSR_NAMES.iter().find(|(_, a)| *a == address).map(|(n, _)| *n)
+}
+
+// rv32 (mxl = 1) with the i and m extensions
+const MISA_VALUE: u32 = 0x4000_0000 | 1 << 8 | 1 << 12;
+
+// mstatus bits we care about, there is only machine mode so mpp always reads as 3
+pub const MSTATUS_MIE: u32 = 1 << 3;
+pub const MSTATUS_MPIE: u32 = 1 << 7;
+const MSTATUS_MPP: u32 = 0x3 << 11;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CsrFile {
+    pub mstatus: u32,
+    pub mie: u32,
+    pub mip: u32,
+    pub mtvec: u32,
+    pub mscratch: u32,
+    pub mepc: u32,
+    pub mcause: u32,
+    pub mtval: u32,
+    pub mhartid: u32,
+    pub cycle: u64,
+    pub instret: u64,
+}
+
+impl CsrFile {
+    // None means theres no csr there, which is an illegal instruction
+    pub fn read(&self, address: u16) -> Option<u32> {
+        Some(match address {
+            MSTATUS => self.mstatus | MSTATUS_MPP,
+            MISA => MISA_VALUE,
+            MIE => self.mie,
+            MTVEC => self.mtvec,
+            MSCRATCH => self.mscratch,
+            MEPC => self.mepc,
+            MCAUSE => self.mcause,
+            MTVAL => self.mtval,
+            MIP => self.mip,
+            // theres no real clock so time just follows the cycle count
+            MCYCLE | CYCLE | TIME => self.cycle as u32,
+            MCYCLEH | CYCLEH | TIMEH => (self.cycle >> 32) as u32,
+            MINSTRET | INSTRET => self.instret as u32,
+            MINSTRETH | INSTRETH => (self.instret >> 32) as u32,
+            MVENDORID | MARCHID | MIMPID => 0,
+            MHARTID => self.mhartid,
+            _ => return None,
+        })
+    }
+
+    // the top two address bits being set means read only, writing those is illegal too
+    pub fn write(&mut self, address: u16, value: u32) -> Option<()> {
+        if address >> 10 == 0b11 {
+            return None;
+        }
+        match address {
+            MSTATUS => self.mstatus = value & (MSTATUS_MIE | MSTATUS_MPIE),
+            // writes to misa are allowed but ignored
+            MISA => (),
+            MIE => self.mie = value,
+            MTVEC => self.mtvec = value,
+            MSCRATCH => self.mscratch = value,
+            // mepc can never have the bottom two bits set without compressed instructions
+            MEPC => self.mepc = value & !0x3,
+            MCAUSE => self.mcause = value,
+            MTVAL => self.mtval = value,
+            MIP => self.mip = value,
+            MCYCLE => self.cycle = (self.cycle & !0xFFFF_FFFF) | value as u64,
+            MCYCLEH => self.cycle = (self.cycle & 0xFFFF_FFFF) | (value as u64) << 32,
+            MINSTRET => self.instret = (self.instret & !0xFFFF_FFFF) | value as u64,
+            MINSTRETH => self.instret = (self.instret & 0xFFFF_FFFF) | (value as u64) << 32,
+            _ => return None,
+        }
+        Some(())
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use crate::csr::csr_name;
+use crate::instruction::{Fields, Format, Instruction};
+use crate::register::abi_name;
+
+// turns machine words back into assembly the assembler would accept, like addi x1, x0, 5
+#[derive(Debug, Default, Clone)]
+pub struct Disassembler {
+    // show registers as sp, a0... instead of x2, x10...
+    pub abi_names: bool,
+    // addresses that have a name, branches and jumps to them show the name instead of an offset
+    pub labels: HashMap<u32, String>,
+}
+
+impl Disassembler {
+    pub fn new(abi_names: bool, labels: HashMap<u32, String>) -> Disassembler {
+        Disassembler { abi_names, labels }
+    }
+
+    fn reg(&self, r: u8) -> String {
+        if self.abi_names {
+            abi_name(r).to_string()
+        } else {
+            format!("x{}", r)
+        }
+    }
+
+    // branch and jump targets, pc is the address of the instruction doing the jumping
+    fn target(&self, pc: u32, offset: i32) -> String {
+        match self.labels.get(&pc.wrapping_add(offset as u32)) {
+            Some(label) => label.clone(),
+            None => offset.to_string(),
+        }
+    }
+
+    fn csr(&self, csr: u16) -> String {
+        match csr_name(csr) {
+            Some(name) => name.to_string(),
+            None => format!("0x{:x}", csr),
+        }
+    }
+
+    // anything that isnt a valid encoding comes back as a .word so it still shows up
+    pub fn disassemble(&self, word: u32, pc: u32) -> String {
+        match Instruction::decode(word) {
+            Some(instruction) => self.instruction(&instruction, pc),
+            None => format!(".word 0x{:08x}", word),
+        }
+    }
+
+    // pc is where the instruction is, for working out branch and jump targets
+    pub fn instruction(&self, instruction: &Instruction, pc: u32) -> String {
+        let name = instruction.name();
+        let Fields { rd, rs1, rs2, imm } = instruction.fields();
+        match instruction.format() {
+            Format::R => format!("{} {}, {}, {}", name, self.reg(rd), self.reg(rs1), self.reg(rs2)),
+            Format::I | Format::Shift => format!("{} {}, {}, {}", name, self.reg(rd), self.reg(rs1), imm),
+            Format::Load => format!("{} {}, {}({})", name, self.reg(rd), imm, self.reg(rs1)),
+            Format::Store => format!("{} {}, {}({})", name, self.reg(rs2), imm, self.reg(rs1)),
+            Format::Branch => format!("{} {}, {}, {}", name, self.reg(rs1), self.reg(rs2), self.target(pc, imm)),
+            Format::Upper => format!("{} {}, 0x{:x}", name, self.reg(rd), (imm as u32) >> 12),
+            Format::Jump => format!("{} {}, {}", name, self.reg(rd), self.target(pc, imm)),
+            Format::Csr => format!("{} {}, {}, {}", name, self.reg(rd), self.csr(imm as u16), self.reg(rs1)),
+            // the i versions have a 5 bit uimm where rs1 normally goes
+            Format::CsrImm => format!("{} {}, {}, {}", name, self.reg(rd), self.csr(imm as u16), rs1),
+            Format::System => name.to_string(),
+        }
+    }
+}
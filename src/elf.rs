@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+// loading statically linked rv32 executables, like what riscv32-unknown-elf-gcc makes.
+// only the bits needed to run one are read: the loadable segments, the entry point and
+// the symbol table so the ui has names for addresses
+
+const MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+const CLASS_32: u8 = 1;
+const LITTLE_ENDIAN: u8 = 1;
+const TYPE_EXEC: u16 = 2;
+const MACHINE_RISCV: u16 = 0xF3;
+
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+// symbol types worth showing, anything else is sections, files and so on
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+// the file isnt an elf we can run, or it doesnt fit in memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfError {
+    pub message: String,
+}
+
+impl ElfError {
+    pub(crate) fn new(message: impl Into<String>) -> ElfError {
+        ElfError { message: message.into() }
+    }
+}
+
+impl Display for ElfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ElfError {}
+
+// a chunk of memory to fill in. size can be bigger than data, the rest is bss and gets zeroed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub address: u32,
+    pub data: Vec<u8>,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Elf {
+    pub entry: u32,
+    pub segments: Vec<Segment>,
+    // names and addresses of the functions and variables, sorted by address
+    pub symbols: Vec<(String, u32)>,
+}
+
+// all the reads are bounds checked since the file could be anything.
+// offsets are u64 so adding up header offsets from a broken file cant overflow
+fn slice(bytes: &[u8], offset: u64, len: u64) -> Result<&[u8], ElfError> {
+    bytes.get(offset as usize..(offset + len) as usize).ok_or(ElfError::new("file is truncated"))
+}
+
+fn half(bytes: &[u8], offset: u64) -> Result<u16, ElfError> {
+    let b = slice(bytes, offset, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn word(bytes: &[u8], offset: u64) -> Result<u32, ElfError> {
+    let b = slice(bytes, offset, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// strings in the string table just run until a 0
+fn string(bytes: &[u8], offset: u64) -> Result<String, ElfError> {
+    let rest = bytes.get(offset as usize..).ok_or(ElfError::new("file is truncated"))?;
+    let end = rest.iter().position(|b| *b == 0).ok_or(ElfError::new("symbol name is not terminated"))?;
+    Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+}
+
+impl Elf {
+    pub fn is_elf(bytes: &[u8]) -> bool {
+        bytes.starts_with(&MAGIC)
+    }
+
+    pub fn open_file(filename: &str) -> Result<Elf, ElfError> {
+        let bytes = fs::read(filename).map_err(|e| ElfError::new(format!("could not read file: {}", e)))?;
+        Elf::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Elf, ElfError> {
+        if !Elf::is_elf(bytes) {
+            return Err(ElfError::new("not an elf file"));
+        }
+        if slice(bytes, 4, 2)? != [CLASS_32, LITTLE_ENDIAN] {
+            return Err(ElfError::new("only 32 bit little endian elf files can be run"));
+        }
+        if half(bytes, 0x12)? != MACHINE_RISCV {
+            return Err(ElfError::new("not a risc-v elf file"));
+        }
+        // object files still need linking, theres nowhere to put them yet
+        if half(bytes, 0x10)? != TYPE_EXEC {
+            return Err(ElfError::new("only statically linked executables can be run"));
+        }
+
+        let entry = word(bytes, 0x18)?;
+        let segments = Elf::segments(bytes)?;
+        let mut symbols = Elf::symbols(bytes)?;
+        symbols.sort_by_key(|(name, address)| (*address, name.clone()));
+
+        Ok(Elf { entry, segments, symbols })
+    }
+
+    // the program headers say what goes where, only the load ones matter
+    fn segments(bytes: &[u8]) -> Result<Vec<Segment>, ElfError> {
+        let offset = word(bytes, 0x1C)? as u64;
+        let size = half(bytes, 0x2A)? as u64;
+        let count = half(bytes, 0x2C)? as u64;
+
+        let mut segments = vec![];
+        for i in 0..count {
+            let header = offset + i * size;
+            if word(bytes, header)? != PT_LOAD {
+                continue;
+            }
+            let file_offset = word(bytes, header + 4)? as u64;
+            let address = word(bytes, header + 8)?;
+            let file_size = word(bytes, header + 16)?;
+            let size = word(bytes, header + 20)?;
+            if file_size > size {
+                return Err(ElfError::new(format!("segment at 0x{:x} has more data than room for it", address)));
+            }
+            segments.push(Segment {
+                address,
+                data: slice(bytes, file_offset, file_size as u64)?.to_vec(),
+                size,
+            });
+        }
+        Ok(segments)
+    }
+
+    // stripped files dont have a symbol table, thats fine theres just no names
+    fn symbols(bytes: &[u8]) -> Result<Vec<(String, u32)>, ElfError> {
+        let offset = word(bytes, 0x20)? as u64;
+        let size = half(bytes, 0x2E)? as u64;
+        let count = half(bytes, 0x30)? as u64;
+        let section = |i: u64| offset + i * size;
+
+        let mut symbols = vec![];
+        for i in 0..count {
+            let header = section(i);
+            if word(bytes, header + 4)? != SHT_SYMTAB {
+                continue;
+            }
+            let table = word(bytes, header + 16)? as u64;
+            let table_size = word(bytes, header + 20)? as u64;
+            // the names are in the string table this one links to
+            let strings = word(bytes, section(word(bytes, header + 24)? as u64) + 16)? as u64;
+
+            // entry 0 is always empty
+            for entry in (16..table_size).step_by(16) {
+                let symbol = table + entry;
+                let kind = slice(bytes, symbol + 12, 1)?[0] & 0xF;
+                let defined = half(bytes, symbol + 14)? != 0;
+                if !defined || !matches!(kind, STT_NOTYPE | STT_OBJECT | STT_FUNC) {
+                    continue;
+                }
+                let name = string(bytes, strings + word(bytes, symbol)? as u64)?;
+                // compiler made local labels and mapping symbols arent useful to look at
+                if name.is_empty() || name.starts_with(".L") || name.starts_with('$') {
+                    continue;
+                }
+                symbols.push((name, word(bytes, symbol + 4)?));
+            }
+        }
+        Ok(symbols)
+    }
+}
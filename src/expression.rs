@@ -0,0 +1,283 @@
+// operand expressions like (end - start) / 4 or %hi(buffer). these get worked out by the
+// assembler once it knows where all the labels are
+
+// what names in an expression mean, the assembler knows the labels and .equ constants
+pub trait Symbols {
+    // the value and whether its a label (an address) or just a constant
+    fn symbol(&self, name: &str) -> Option<(i64, bool)>;
+    // the low part for %pcrel_lo(label), where label is on the auipc with the %pcrel_hi
+    fn pcrel_lo(&self, label: &str) -> Result<i64, String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Value {
+    pub value: i64,
+    // true when its a label plus or minus some constant, branches and jumps take those as
+    // a target address instead of an offset
+    pub address: bool,
+}
+
+// splits a 32 bit value into the upper 20 bits for lui/auipc and the lower 12 for addi.
+// addi sign extends so if bit 11 is set the upper part has to be one bigger to cancel it out
+pub fn split_hi_lo(value: i32) -> (i32, i32) {
+    let hi = (value.wrapping_add(0x800) >> 12) & 0xFFFFF;
+    let lo = (value << 20) >> 20;
+    (hi, lo)
+}
+
+// the character after a backslash in a string or char literal
+pub fn escape(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' | '"' | '\'' => Some(c),
+        _ => None,
+    }
+}
+
+// literals can be decimal, 0x hex, 0b binary, 0o octal (all with an optional minus)
+// or a character like 'a' or '\n'. underscores can go between digits so 0xFFFF_0000 works
+pub fn parse_literal(text: &str) -> Option<i64> {
+    if let Some(c) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        let mut chars = c.chars();
+        let c = match (chars.next(), chars.next(), chars.next()) {
+            (Some('\\'), Some(e), None) => escape(e)?,
+            (Some(c), None, None) if c != '\\' && c != '\'' => c,
+            _ => return None,
+        };
+        return Some(c as i64);
+    }
+
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (radix, digits) = match text.get(..2) {
+        Some("0x" | "0X") => (16, &text[2..]),
+        Some("0b" | "0B") => (2, &text[2..]),
+        Some("0o" | "0O") => (8, &text[2..]),
+        _ => (10, text),
+    };
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    // from_str_radix would take a sign here too, only digits are allowed
+    let digits = digits.replace('_', "");
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let value = i64::from_str_radix(&digits, radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    // %hi, %lo, %pcrel_hi, %pcrel_lo
+    Function(String),
+    Op(&'static str),
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$'
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        i += 1;
+        match c {
+            _ if c.is_whitespace() => (),
+            '\'' => {
+                // up to the closing quote, skipping over an escaped one
+                while i < chars.len() && chars[i] != '\'' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(chars.len());
+                let literal = chars[start..i].iter().collect::<String>();
+                let value = parse_literal(&literal).ok_or(format!("{} is not a valid character", literal))?;
+                tokens.push(Token::Number(value));
+            },
+            '%' if chars.get(i).is_some_and(|c| c.is_ascii_alphabetic()) => {
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Function(chars[start + 1..i].iter().collect()));
+            },
+            _ if is_name_char(c) => {
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                let word = chars[start..i].iter().collect::<String>();
+                if c.is_ascii_digit() {
+                    let value = parse_literal(&word).ok_or(format!("{} is not a valid number", word))?;
+                    tokens.push(Token::Number(value));
+                } else {
+                    tokens.push(Token::Name(word));
+                }
+            },
+            '<' | '>' if chars.get(i) == Some(&c) => {
+                i += 1;
+                tokens.push(Token::Op(if c == '<' { "<<" } else { ">>" }));
+            },
+            '+' => tokens.push(Token::Op("+")),
+            '-' => tokens.push(Token::Op("-")),
+            '*' => tokens.push(Token::Op("*")),
+            '/' => tokens.push(Token::Op("/")),
+            '%' => tokens.push(Token::Op("%")),
+            '&' => tokens.push(Token::Op("&")),
+            '|' => tokens.push(Token::Op("|")),
+            '^' => tokens.push(Token::Op("^")),
+            '~' => tokens.push(Token::Op("~")),
+            '(' => tokens.push(Token::Op("(")),
+            ')' => tokens.push(Token::Op(")")),
+            _ => return Err(format!("{} is not allowed in an expression", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+// recursive descent with the usual c precedence, lowest first: | ^ & << >> + - * / % then unary.
+// labels are counted as they get added and subtracted so label - label is a plain number again
+struct Parser<'a, S: Symbols> {
+    // the whole expression, for errors
+    text: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+    pc: u32,
+    symbols: &'a S,
+}
+
+// a value while parsing, labels is how many addresses got added in (minus subtracted)
+#[derive(Clone, Copy)]
+struct Partial {
+    value: i64,
+    labels: i64,
+}
+
+impl Partial {
+    fn number(value: i64) -> Partial {
+        Partial { value, labels: 0 }
+    }
+}
+
+// precedence levels from loosest to tightest
+const LEVELS: [&[&str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/", "%"]];
+
+impl<S: Symbols> Parser<'_, S> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect_close(&mut self) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(")")) => Ok(()),
+            _ => Err("missing a closing )".to_string()),
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Partial, String> {
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.peek()
+            && LEVELS[level].contains(op) {
+            let op = *op;
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            let (a, b) = (left.value, right.value);
+            left = match op {
+                "+" => Partial { value: a.wrapping_add(b), labels: left.labels + right.labels },
+                "-" => Partial { value: a.wrapping_sub(b), labels: left.labels - right.labels },
+                "*" => Partial::number(a.wrapping_mul(b)),
+                "/" | "%" if b == 0 => return Err("division by zero".to_string()),
+                // wrapping_shl would only use the low 6 bits, so 1 << 64 would quietly be 1
+                "<<" | ">>" if !(0..64).contains(&b) => {
+                    return Err(format!("{} shifts by {}, it has to be 0 to 63", self.text.trim(), b));
+                },
+                "/" => Partial::number(a.wrapping_div(b)),
+                "%" => Partial::number(a.wrapping_rem(b)),
+                "<<" => Partial::number(a.wrapping_shl(b as u32)),
+                ">>" => Partial::number(a.wrapping_shr(b as u32)),
+                "&" => Partial::number(a & b),
+                "^" => Partial::number(a ^ b),
+                _ => Partial::number(a | b),
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Partial, String> {
+        match self.next() {
+            Some(Token::Op("-")) => self.unary().map(|p| Partial { value: p.value.wrapping_neg(), labels: -p.labels }),
+            Some(Token::Op("+")) => self.unary(),
+            Some(Token::Op("~")) => self.unary().map(|p| Partial::number(!p.value)),
+            Some(Token::Op("(")) => {
+                let inner = self.binary(0)?;
+                self.expect_close()?;
+                Ok(inner)
+            },
+            Some(Token::Number(value)) => Ok(Partial::number(value)),
+            Some(Token::Name(name)) => match self.symbols.symbol(&name) {
+                Some((value, label)) => Ok(Partial { value, labels: label as i64 }),
+                None => Err(format!("{} is not a number or a known label", name)),
+            },
+            Some(Token::Function(function)) => self.function(&function),
+            Some(Token::Op(op)) => Err(format!("expected a value but found {}", op)),
+            None => Err("expected a value".to_string()),
+        }
+    }
+
+    // %hi and %lo split an address for lui + addi, the pcrel ones do the same for auipc
+    fn function(&mut self, function: &str) -> Result<Partial, String> {
+        if self.next() != Some(Token::Op("(")) {
+            return Err(format!("%{} needs its argument in brackets", function));
+        }
+        let value = match function {
+            "hi" => split_hi_lo(self.binary(0)?.value as i32).0,
+            "lo" => split_hi_lo(self.binary(0)?.value as i32).1,
+            "pcrel_hi" => split_hi_lo((self.binary(0)?.value as i32).wrapping_sub(self.pc as i32)).0,
+            "pcrel_lo" => match self.next() {
+                Some(Token::Name(label)) => self.symbols.pcrel_lo(&label)? as i32,
+                _ => return Err("%pcrel_lo needs the label of its auipc".to_string()),
+            },
+            _ => return Err(format!("%{} is not a known operator", function)),
+        };
+        self.expect_close()?;
+        Ok(Partial::number(value as i64))
+    }
+}
+
+// pc is the address of the instruction the expression is in, for %pcrel_hi
+pub fn evaluate(text: &str, pc: u32, symbols: &impl Symbols) -> Result<Value, String> {
+    let mut parser = Parser {
+        text,
+        tokens: tokenize(text)?,
+        position: 0,
+        pc,
+        symbols,
+    };
+    let result = parser.binary(0)?;
+    if parser.peek().is_some() {
+        return Err(format!("{} is not a valid expression", text));
+    }
+    Ok(Value {
+        value: result.value,
+        address: result.labels == 1,
+    })
+}
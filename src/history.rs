@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+use crate::bus::{Bus, MemoryMap};
+use crate::csr::CsrFile;
+use crate::instruction::Instruction;
+
+// how many steps can be undone unless the cpu gets told otherwise
+pub const HISTORY_LIMIT: usize = 10_000;
+
+// what one step changed, holding the old values so it can be put back
+#[derive(Debug, Clone)]
+pub(crate) struct Delta {
+    pub(crate) pc: u32,
+    // register number and what it had before
+    pub(crate) registers: Vec<(u8, u32)>,
+    // old bytes in the order they got written, so putting them back in reverse is right
+    // even when the same byte gets written twice
+    pub(crate) memory: Vec<(u32, u8)>,
+    // cycle and instret move every step so theres no point only keeping the ones that changed
+    pub(crate) csrs: CsrFile,
+    pub(crate) instruction: Option<Instruction>,
+    // where the heap ended, sbrk and brk move it
+    pub(crate) brk: u32,
+}
+
+// the last limit steps, oldest at the front. things outside the cpu like uart output,
+// typed input and files cant be taken back so undoing only covers the cpu and memory
+pub(crate) struct History {
+    steps: VecDeque<Delta>,
+    limit: usize,
+    // the step thats running, None when nothing is being kept
+    current: Option<Delta>,
+}
+
+impl History {
+    pub(crate) fn new(limit: usize) -> History {
+        History { steps: VecDeque::new(), limit, current: None }
+    }
+
+    // anything older than the new limit gets dropped
+    pub(crate) fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        while self.steps.len() > limit {
+            self.steps.pop_front();
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.steps.clear();
+        self.current = None;
+    }
+
+    // call before a step with what it might change
+    pub(crate) fn begin(&mut self, pc: u32, csrs: CsrFile, instruction: Option<Instruction>, brk: u32) {
+        if self.limit == 0 {
+            return;
+        }
+        self.current = Some(Delta { pc, registers: vec![], memory: vec![], csrs, instruction, brk });
+    }
+
+    // call before writing size bytes. only ram and rom are kept, a device register can read
+    // back as something else entirely (the uart's iir is fcr when written) so they get left alone
+    pub(crate) fn save_memory(&mut self, bus: &MemoryMap, address: u32, size: u32) {
+        for i in 0..size {
+            let address = address.wrapping_add(i);
+            if let Some(byte) = bus.peek(address) {
+                self.save_byte(bus, address, byte);
+            }
+        }
+    }
+
+    // a byte thats already been written, with what it was before
+    pub(crate) fn save_byte(&mut self, bus: &MemoryMap, address: u32, old: u8) {
+        if let Some(delta) = &mut self.current
+            && bus.is_memory_at(address) {
+            delta.memory.push((address, old));
+        }
+    }
+
+    // the step didnt happen after all, forget it
+    pub(crate) fn cancel(&mut self) {
+        self.current = None;
+    }
+
+    // call after with the registers from before and after the step
+    pub(crate) fn finish(&mut self, before: &[u32; 32], after: &[u32; 32]) {
+        let Some(mut delta) = self.current.take() else {
+            return;
+        };
+        delta.registers = (0..32).filter(|i| before[*i] != after[*i]).map(|i| (i as u8, before[i])).collect();
+        if self.steps.len() == self.limit {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(delta);
+    }
+
+    // the newest step, taken off so undoing it twice isnt possible
+    pub(crate) fn pop(&mut self) -> Option<Delta> {
+        self.steps.pop_back()
+    }
+}
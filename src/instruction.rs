@@ -1,15 +1,5 @@
 use std::fmt::{Debug, Formatter};
 
-#[derive(Debug)]
-pub enum InstructionType {
-    RInstr,
-    IInstr,
-    BInstr,
-    SInstr,
-  //  UInstr,
-   // JInstr,
-}
-
 #[inline(always)]
 fn opcode_f_u32(n: u32) -> u8 {
     (n & 0x7F) as u8
@@ -32,7 +22,7 @@ fn rs1_f_u32(n: u32) -> u8 {
 
 #[inline(always)]
 fn rs2_f_u32(n: u32) -> u8 {
-    ((n >> 20) & 0x7) as u8
+    ((n >> 20) & 0x1F) as u8
 }
 
 #[inline(always)]
@@ -50,7 +40,7 @@ fn imm_i_f_u32(n: u32) -> i16 {
 // 12|10:5 ---- 4:1|11 which is why it looks so weird
 #[inline(always)]
 fn imm_b_f_u32(n: u32) -> i16 {
-    (((((n>>31) & 0x1) << 11 | ((n>>7) & 0x1) << 10 |  ((n>>25)&0x3F) << 5 |  ((n >> 8) & 0xF) << 1  ) as i16) << 4) >> 4
+    (((((n>>31) & 0x1) << 12 | ((n>>7) & 0x1) << 11 |  ((n>>25)&0x3F) << 5 |  ((n >> 8) & 0xF) << 1  ) as i16) << 3) >> 3
 }
 
 // slightly less weird and its split up like
@@ -60,6 +50,19 @@ fn imm_s_f_u32(n: u32) -> i16 {
     ((((n >> 25 & 0x7F) << 5 |  (n >> 7) & 0x1F) as i16) << 4) >> 4
 }
 
+// u instructions just keep the top 20 bits where they are
+#[inline(always)]
+fn imm_u_f_u32(n: u32) -> i32 {
+    (n & 0xFFFFF000) as i32
+}
+
+// j instructions are the worst one, split up like
+// 20|10:1|11|19:12
+#[inline(always)]
+fn imm_j_f_u32(n: u32) -> i32 {
+    ((((n >> 31) & 0x1) << 20 | ((n >> 21) & 0x3FF) << 1 | ((n >> 20) & 0x1) << 11 | ((n >> 12) & 0xFF) << 12) as i32) << 11 >> 11
+}
+
 
 pub struct RInstruction {
     pub opcode: u8,
@@ -183,3 +186,469 @@ impl Debug for SInstruction {
         )
     }
 }
+
+pub struct UInstruction {
+    pub opcode: u8,
+    pub rd: u8,
+    pub imm: i32,
+}
+
+impl UInstruction {
+    pub fn new(n: u32) -> Self {
+        Self {
+            opcode: opcode_f_u32(n),
+            rd: rd_f_u32(n),
+            imm: imm_u_f_u32(n),
+        }
+    }
+}
+
+impl Debug for UInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "OPCODE: {:b} | RD: R{} | IMM: {} ",
+            self.opcode, self.rd, self.imm
+        )
+    }
+}
+
+pub struct JInstruction {
+    pub opcode: u8,
+    pub rd: u8,
+    pub imm: i32,
+}
+
+impl JInstruction {
+    pub fn new(n: u32) -> Self {
+        Self {
+            opcode: opcode_f_u32(n),
+            rd: rd_f_u32(n),
+            imm: imm_j_f_u32(n),
+        }
+    }
+}
+
+impl Debug for JInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "OPCODE: {:b} | RD: R{} | IMM: {} ",
+            self.opcode, self.rd, self.imm
+        )
+    }
+}
+
+// which operands an instruction takes and where they go in the word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // rd, rs1, rs2
+    R,
+    // rd, rs1, imm
+    I,
+    // rd, rs1, shamt. i format but the top of imm is a funct7
+    Shift,
+    // rd, imm(rs1), loads and jalr
+    Load,
+    // rs2, imm(rs1)
+    Store,
+    // rs1, rs2, offset
+    Branch,
+    // rd, upper 20 bits
+    Upper,
+    // rd, offset
+    Jump,
+    // rd, csr, rs1
+    Csr,
+    // rd, csr, uimm
+    CsrImm,
+    // no operands at all, ecall and friends
+    System,
+}
+
+// the operands of any instruction, whatever the format doesnt use is 0.
+// imm is the shamt for shifts and the csr address for csr ones, and rs1 holds the uimm for csrrwi and co.
+// u instructions have the imm already shifted up so the low 12 bits are 0
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Fields {
+    pub rd: u8,
+    pub rs1: u8,
+    pub rs2: u8,
+    pub imm: i32,
+}
+
+// name, format, opcode, funct3, funct7.
+// shifts use funct7 for the top bits of imm, the system ones use it for the whole imm
+const ENCODINGS: [(&str, Format, u8, u8, u16); 54] = [
+    ("add", Format::R, 0x33, 0x0, 0x00),
+    ("sub", Format::R, 0x33, 0x0, 0x20),
+    ("sll", Format::R, 0x33, 0x1, 0x00),
+    ("slt", Format::R, 0x33, 0x2, 0x00),
+    ("sltu", Format::R, 0x33, 0x3, 0x00),
+    ("xor", Format::R, 0x33, 0x4, 0x00),
+    ("srl", Format::R, 0x33, 0x5, 0x00),
+    ("sra", Format::R, 0x33, 0x5, 0x20),
+    ("or", Format::R, 0x33, 0x6, 0x00),
+    ("and", Format::R, 0x33, 0x7, 0x00),
+    ("mul", Format::R, 0x33, 0x0, 0x01),
+    ("mulh", Format::R, 0x33, 0x1, 0x01),
+    ("mulhsu", Format::R, 0x33, 0x2, 0x01),
+    ("mulhu", Format::R, 0x33, 0x3, 0x01),
+    ("div", Format::R, 0x33, 0x4, 0x01),
+    ("divu", Format::R, 0x33, 0x5, 0x01),
+    ("rem", Format::R, 0x33, 0x6, 0x01),
+    ("remu", Format::R, 0x33, 0x7, 0x01),
+    ("addi", Format::I, 0x13, 0x0, 0x00),
+    ("slti", Format::I, 0x13, 0x2, 0x00),
+    ("sltiu", Format::I, 0x13, 0x3, 0x00),
+    ("xori", Format::I, 0x13, 0x4, 0x00),
+    ("ori", Format::I, 0x13, 0x6, 0x00),
+    ("andi", Format::I, 0x13, 0x7, 0x00),
+    ("slli", Format::Shift, 0x13, 0x1, 0x00),
+    ("srli", Format::Shift, 0x13, 0x5, 0x00),
+    ("srai", Format::Shift, 0x13, 0x5, 0x20),
+    ("lb", Format::Load, 0x03, 0x0, 0x00),
+    ("lh", Format::Load, 0x03, 0x1, 0x00),
+    ("lw", Format::Load, 0x03, 0x2, 0x00),
+    ("lbu", Format::Load, 0x03, 0x4, 0x00),
+    ("lhu", Format::Load, 0x03, 0x5, 0x00),
+    ("jalr", Format::Load, 0x67, 0x0, 0x00),
+    ("sb", Format::Store, 0x23, 0x0, 0x00),
+    ("sh", Format::Store, 0x23, 0x1, 0x00),
+    ("sw", Format::Store, 0x23, 0x2, 0x00),
+    ("beq", Format::Branch, 0x63, 0x0, 0x00),
+    ("bne", Format::Branch, 0x63, 0x1, 0x00),
+    ("blt", Format::Branch, 0x63, 0x4, 0x00),
+    ("bge", Format::Branch, 0x63, 0x5, 0x00),
+    ("bltu", Format::Branch, 0x63, 0x6, 0x00),
+    ("bgeu", Format::Branch, 0x63, 0x7, 0x00),
+    ("lui", Format::Upper, 0x37, 0x0, 0x00),
+    ("auipc", Format::Upper, 0x17, 0x0, 0x00),
+    ("jal", Format::Jump, 0x6F, 0x0, 0x00),
+    ("csrrw", Format::Csr, 0x73, 0x1, 0x00),
+    ("csrrs", Format::Csr, 0x73, 0x2, 0x00),
+    ("csrrc", Format::Csr, 0x73, 0x3, 0x00),
+    ("csrrwi", Format::CsrImm, 0x73, 0x5, 0x00),
+    ("csrrsi", Format::CsrImm, 0x73, 0x6, 0x00),
+    ("csrrci", Format::CsrImm, 0x73, 0x7, 0x00),
+    ("ecall", Format::System, 0x73, 0x0, 0x000),
+    ("ebreak", Format::System, 0x73, 0x0, 0x001),
+    ("mret", Format::System, 0x73, 0x0, 0x302),
+];
+
+fn lookup(name: &str) -> Option<&'static (&'static str, Format, u8, u8, u16)> {
+    ENCODINGS.iter().find(|e| e.0 == name)
+}
+
+// every instruction the cpu knows, with its operands already pulled out of the word.
+// the cpu runs these and the assembler builds them, so theres only one place that knows the bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Add { rd: u8, rs1: u8, rs2: u8 },
+    Sub { rd: u8, rs1: u8, rs2: u8 },
+    Sll { rd: u8, rs1: u8, rs2: u8 },
+    Slt { rd: u8, rs1: u8, rs2: u8 },
+    Sltu { rd: u8, rs1: u8, rs2: u8 },
+    Xor { rd: u8, rs1: u8, rs2: u8 },
+    Srl { rd: u8, rs1: u8, rs2: u8 },
+    Sra { rd: u8, rs1: u8, rs2: u8 },
+    Or { rd: u8, rs1: u8, rs2: u8 },
+    And { rd: u8, rs1: u8, rs2: u8 },
+    Mul { rd: u8, rs1: u8, rs2: u8 },
+    Mulh { rd: u8, rs1: u8, rs2: u8 },
+    Mulhsu { rd: u8, rs1: u8, rs2: u8 },
+    Mulhu { rd: u8, rs1: u8, rs2: u8 },
+    Div { rd: u8, rs1: u8, rs2: u8 },
+    Divu { rd: u8, rs1: u8, rs2: u8 },
+    Rem { rd: u8, rs1: u8, rs2: u8 },
+    Remu { rd: u8, rs1: u8, rs2: u8 },
+    Addi { rd: u8, rs1: u8, imm: i32 },
+    Slti { rd: u8, rs1: u8, imm: i32 },
+    Sltiu { rd: u8, rs1: u8, imm: i32 },
+    Xori { rd: u8, rs1: u8, imm: i32 },
+    Ori { rd: u8, rs1: u8, imm: i32 },
+    Andi { rd: u8, rs1: u8, imm: i32 },
+    Slli { rd: u8, rs1: u8, shamt: u8 },
+    Srli { rd: u8, rs1: u8, shamt: u8 },
+    Srai { rd: u8, rs1: u8, shamt: u8 },
+    Lb { rd: u8, rs1: u8, imm: i32 },
+    Lh { rd: u8, rs1: u8, imm: i32 },
+    Lw { rd: u8, rs1: u8, imm: i32 },
+    Lbu { rd: u8, rs1: u8, imm: i32 },
+    Lhu { rd: u8, rs1: u8, imm: i32 },
+    Jalr { rd: u8, rs1: u8, imm: i32 },
+    Sb { rs1: u8, rs2: u8, imm: i32 },
+    Sh { rs1: u8, rs2: u8, imm: i32 },
+    Sw { rs1: u8, rs2: u8, imm: i32 },
+    Beq { rs1: u8, rs2: u8, imm: i32 },
+    Bne { rs1: u8, rs2: u8, imm: i32 },
+    Blt { rs1: u8, rs2: u8, imm: i32 },
+    Bge { rs1: u8, rs2: u8, imm: i32 },
+    Bltu { rs1: u8, rs2: u8, imm: i32 },
+    Bgeu { rs1: u8, rs2: u8, imm: i32 },
+    // imm has the low 12 bits cleared, its the value that gets loaded / added
+    Lui { rd: u8, imm: i32 },
+    Auipc { rd: u8, imm: i32 },
+    Jal { rd: u8, imm: i32 },
+    Csrrw { rd: u8, rs1: u8, csr: u16 },
+    Csrrs { rd: u8, rs1: u8, csr: u16 },
+    Csrrc { rd: u8, rs1: u8, csr: u16 },
+    Csrrwi { rd: u8, uimm: u8, csr: u16 },
+    Csrrsi { rd: u8, uimm: u8, csr: u16 },
+    Csrrci { rd: u8, uimm: u8, csr: u16 },
+    Ecall,
+    Ebreak,
+    Mret,
+}
+
+impl Instruction {
+    // None if the word isnt a valid instruction. bits that have to be a certain value
+    // (like funct7 or the zeros around ecall) are checked, so every word that decodes
+    // encodes back to exactly the same word
+    pub fn decode(word: u32) -> Option<Instruction> {
+        let opcode = opcode_f_u32(word);
+        let funct3 = funct3_f_u32(word);
+        let funct7 = funct7_f_u32(word) as u16;
+
+        let (name, format, ..) = ENCODINGS.iter().find(|(_, format, op, f3, f7)| {
+            *op == opcode && match format {
+                Format::Upper | Format::Jump => true,
+                Format::R | Format::Shift => *f3 == funct3 && *f7 == funct7,
+                // rd, funct3 and rs1 are all 0 and the imm picks which one it is
+                Format::System => word >> 7 == (*f7 as u32) << 13,
+                _ => *f3 == funct3,
+            }
+        })?;
+
+        let fields = match format {
+            Format::R => {
+                let ins = RInstruction::new(word);
+                Fields { rd: ins.rd, rs1: ins.rs1, rs2: ins.rs2, imm: 0 }
+            },
+            Format::I | Format::Load => {
+                let ins = IInstruction::new(word);
+                Fields { rd: ins.rd, rs1: ins.rs1, rs2: 0, imm: ins.imm as i32 }
+            },
+            Format::Shift => {
+                let ins = IInstruction::new(word);
+                Fields { rd: ins.rd, rs1: ins.rs1, rs2: 0, imm: (ins.imm & 0x1F) as i32 }
+            },
+            // the csr address isnt signed
+            Format::Csr | Format::CsrImm => {
+                let ins = IInstruction::new(word);
+                Fields { rd: ins.rd, rs1: ins.rs1, rs2: 0, imm: (ins.imm as u16 & 0xFFF) as i32 }
+            },
+            Format::Store => {
+                let ins = SInstruction::new(word);
+                Fields { rd: 0, rs1: ins.rs1, rs2: ins.rs2, imm: ins.imm as i32 }
+            },
+            Format::Branch => {
+                let ins = BInstruction::new(word);
+                Fields { rd: 0, rs1: ins.rs1, rs2: ins.rs2, imm: ins.imm as i32 }
+            },
+            Format::Upper => {
+                let ins = UInstruction::new(word);
+                Fields { rd: ins.rd, imm: ins.imm, ..Fields::default() }
+            },
+            Format::Jump => {
+                let ins = JInstruction::new(word);
+                Fields { rd: ins.rd, imm: ins.imm, ..Fields::default() }
+            },
+            Format::System => Fields::default(),
+        };
+        Instruction::new(name, fields)
+    }
+
+    // operands that dont fit just get cut down to their bits
+    pub fn encode(&self) -> u32 {
+        let (_, format, opcode, funct3, funct7) = *lookup(self.name()).expect("every instruction is in the table");
+        let Fields { rd, rs1, rs2, imm } = self.fields();
+        let (rd, rs1, rs2, imm, funct7) = ((rd & 0x1F) as u32, (rs1 & 0x1F) as u32, (rs2 & 0x1F) as u32, imm as u32, funct7 as u32);
+        let base = opcode as u32 | (funct3 as u32) << 12;
+
+        base | match format {
+            Format::R => rd << 7 | rs1 << 15 | rs2 << 20 | funct7 << 25,
+            Format::I | Format::Load | Format::Csr | Format::CsrImm => rd << 7 | rs1 << 15 | (imm & 0xFFF) << 20,
+            Format::Shift => rd << 7 | rs1 << 15 | (imm & 0x1F) << 20 | funct7 << 25,
+            // 11:5 ---- 4:0
+            Format::Store => (imm & 0x1F) << 7 | rs1 << 15 | rs2 << 20 | (imm >> 5 & 0x7F) << 25,
+            // 12|10:5 ---- 4:1|11
+            Format::Branch => (imm >> 11 & 0x1) << 7 | (imm >> 1 & 0xF) << 8 | rs1 << 15 | rs2 << 20
+                | (imm >> 5 & 0x3F) << 25 | (imm >> 12 & 0x1) << 31,
+            Format::Upper => rd << 7 | (imm & 0xFFFFF000),
+            // 20|10:1|11|19:12
+            Format::Jump => rd << 7 | (imm >> 12 & 0xFF) << 12 | (imm >> 11 & 0x1) << 20
+                | (imm >> 1 & 0x3FF) << 21 | (imm >> 20 & 0x1) << 31,
+            Format::System => funct7 << 20,
+        }
+    }
+
+    // builds an instruction from its lowercase name, None if theres no instruction called that.
+    // fields the format doesnt use are ignored
+    pub fn new(name: &str, fields: Fields) -> Option<Instruction> {
+        use Instruction::*;
+        let Fields { rd, rs1, rs2, imm } = fields;
+        Some(match name {
+            "add" => Add { rd, rs1, rs2 },
+            "sub" => Sub { rd, rs1, rs2 },
+            "sll" => Sll { rd, rs1, rs2 },
+            "slt" => Slt { rd, rs1, rs2 },
+            "sltu" => Sltu { rd, rs1, rs2 },
+            "xor" => Xor { rd, rs1, rs2 },
+            "srl" => Srl { rd, rs1, rs2 },
+            "sra" => Sra { rd, rs1, rs2 },
+            "or" => Or { rd, rs1, rs2 },
+            "and" => And { rd, rs1, rs2 },
+            "mul" => Mul { rd, rs1, rs2 },
+            "mulh" => Mulh { rd, rs1, rs2 },
+            "mulhsu" => Mulhsu { rd, rs1, rs2 },
+            "mulhu" => Mulhu { rd, rs1, rs2 },
+            "div" => Div { rd, rs1, rs2 },
+            "divu" => Divu { rd, rs1, rs2 },
+            "rem" => Rem { rd, rs1, rs2 },
+            "remu" => Remu { rd, rs1, rs2 },
+            "addi" => Addi { rd, rs1, imm },
+            "slti" => Slti { rd, rs1, imm },
+            "sltiu" => Sltiu { rd, rs1, imm },
+            "xori" => Xori { rd, rs1, imm },
+            "ori" => Ori { rd, rs1, imm },
+            "andi" => Andi { rd, rs1, imm },
+            "slli" => Slli { rd, rs1, shamt: imm as u8 },
+            "srli" => Srli { rd, rs1, shamt: imm as u8 },
+            "srai" => Srai { rd, rs1, shamt: imm as u8 },
+            "lb" => Lb { rd, rs1, imm },
+            "lh" => Lh { rd, rs1, imm },
+            "lw" => Lw { rd, rs1, imm },
+            "lbu" => Lbu { rd, rs1, imm },
+            "lhu" => Lhu { rd, rs1, imm },
+            "jalr" => Jalr { rd, rs1, imm },
+            "sb" => Sb { rs1, rs2, imm },
+            "sh" => Sh { rs1, rs2, imm },
+            "sw" => Sw { rs1, rs2, imm },
+            "beq" => Beq { rs1, rs2, imm },
+            "bne" => Bne { rs1, rs2, imm },
+            "blt" => Blt { rs1, rs2, imm },
+            "bge" => Bge { rs1, rs2, imm },
+            "bltu" => Bltu { rs1, rs2, imm },
+            "bgeu" => Bgeu { rs1, rs2, imm },
+            "lui" => Lui { rd, imm },
+            "auipc" => Auipc { rd, imm },
+            "jal" => Jal { rd, imm },
+            "csrrw" => Csrrw { rd, rs1, csr: imm as u16 },
+            "csrrs" => Csrrs { rd, rs1, csr: imm as u16 },
+            "csrrc" => Csrrc { rd, rs1, csr: imm as u16 },
+            "csrrwi" => Csrrwi { rd, uimm: rs1, csr: imm as u16 },
+            "csrrsi" => Csrrsi { rd, uimm: rs1, csr: imm as u16 },
+            "csrrci" => Csrrci { rd, uimm: rs1, csr: imm as u16 },
+            "ecall" => Ecall,
+            "ebreak" => Ebreak,
+            "mret" => Mret,
+            _ => return None,
+        })
+    }
+
+    // the operands back out in the shape new takes them
+    pub fn fields(&self) -> Fields {
+        use Instruction::*;
+        match *self {
+            Add { rd, rs1, rs2 } | Sub { rd, rs1, rs2 } | Sll { rd, rs1, rs2 } | Slt { rd, rs1, rs2 }
+            | Sltu { rd, rs1, rs2 } | Xor { rd, rs1, rs2 } | Srl { rd, rs1, rs2 } | Sra { rd, rs1, rs2 }
+            | Or { rd, rs1, rs2 } | And { rd, rs1, rs2 } | Mul { rd, rs1, rs2 } | Mulh { rd, rs1, rs2 }
+            | Mulhsu { rd, rs1, rs2 } | Mulhu { rd, rs1, rs2 } | Div { rd, rs1, rs2 } | Divu { rd, rs1, rs2 }
+            | Rem { rd, rs1, rs2 } | Remu { rd, rs1, rs2 } => Fields { rd, rs1, rs2, imm: 0 },
+            Addi { rd, rs1, imm } | Slti { rd, rs1, imm } | Sltiu { rd, rs1, imm } | Xori { rd, rs1, imm }
+            | Ori { rd, rs1, imm } | Andi { rd, rs1, imm } | Lb { rd, rs1, imm } | Lh { rd, rs1, imm }
+            | Lw { rd, rs1, imm } | Lbu { rd, rs1, imm } | Lhu { rd, rs1, imm }
+            | Jalr { rd, rs1, imm } => Fields { rd, rs1, rs2: 0, imm },
+            Slli { rd, rs1, shamt } | Srli { rd, rs1, shamt } | Srai { rd, rs1, shamt } => {
+                Fields { rd, rs1, rs2: 0, imm: shamt as i32 }
+            },
+            Sb { rs1, rs2, imm } | Sh { rs1, rs2, imm } | Sw { rs1, rs2, imm } | Beq { rs1, rs2, imm }
+            | Bne { rs1, rs2, imm } | Blt { rs1, rs2, imm } | Bge { rs1, rs2, imm } | Bltu { rs1, rs2, imm }
+            | Bgeu { rs1, rs2, imm } => Fields { rd: 0, rs1, rs2, imm },
+            Lui { rd, imm } | Auipc { rd, imm } | Jal { rd, imm } => Fields { rd, imm, ..Fields::default() },
+            Csrrw { rd, rs1, csr } | Csrrs { rd, rs1, csr } | Csrrc { rd, rs1, csr } => {
+                Fields { rd, rs1, rs2: 0, imm: csr as i32 }
+            },
+            Csrrwi { rd, uimm, csr } | Csrrsi { rd, uimm, csr } | Csrrci { rd, uimm, csr } => {
+                Fields { rd, rs1: uimm, rs2: 0, imm: csr as i32 }
+            },
+            Ecall | Ebreak | Mret => Fields::default(),
+        }
+    }
+
+    // the name as its written in assembly
+    pub fn name(&self) -> &'static str {
+        use Instruction::*;
+        match self {
+            Add { .. } => "add",
+            Sub { .. } => "sub",
+            Sll { .. } => "sll",
+            Slt { .. } => "slt",
+            Sltu { .. } => "sltu",
+            Xor { .. } => "xor",
+            Srl { .. } => "srl",
+            Sra { .. } => "sra",
+            Or { .. } => "or",
+            And { .. } => "and",
+            Mul { .. } => "mul",
+            Mulh { .. } => "mulh",
+            Mulhsu { .. } => "mulhsu",
+            Mulhu { .. } => "mulhu",
+            Div { .. } => "div",
+            Divu { .. } => "divu",
+            Rem { .. } => "rem",
+            Remu { .. } => "remu",
+            Addi { .. } => "addi",
+            Slti { .. } => "slti",
+            Sltiu { .. } => "sltiu",
+            Xori { .. } => "xori",
+            Ori { .. } => "ori",
+            Andi { .. } => "andi",
+            Slli { .. } => "slli",
+            Srli { .. } => "srli",
+            Srai { .. } => "srai",
+            Lb { .. } => "lb",
+            Lh { .. } => "lh",
+            Lw { .. } => "lw",
+            Lbu { .. } => "lbu",
+            Lhu { .. } => "lhu",
+            Jalr { .. } => "jalr",
+            Sb { .. } => "sb",
+            Sh { .. } => "sh",
+            Sw { .. } => "sw",
+            Beq { .. } => "beq",
+            Bne { .. } => "bne",
+            Blt { .. } => "blt",
+            Bge { .. } => "bge",
+            Bltu { .. } => "bltu",
+            Bgeu { .. } => "bgeu",
+            Lui { .. } => "lui",
+            Auipc { .. } => "auipc",
+            Jal { .. } => "jal",
+            Csrrw { .. } => "csrrw",
+            Csrrs { .. } => "csrrs",
+            Csrrc { .. } => "csrrc",
+            Csrrwi { .. } => "csrrwi",
+            Csrrsi { .. } => "csrrsi",
+            Csrrci { .. } => "csrrci",
+            Ecall => "ecall",
+            Ebreak => "ebreak",
+            Mret => "mret",
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        Instruction::format_of(self.name()).expect("every instruction is in the table")
+    }
+
+    // the format for an instruction name, None if its not one
+    pub fn format_of(name: &str) -> Option<Format> {
+        lookup(name).map(|e| e.1)
+    }
+
+    // every instruction name there is
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        ENCODINGS.iter().map(|e| e.0)
+    }
+}
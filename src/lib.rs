@@ -0,0 +1,32 @@
+// the emulator core without any of the gui, so other crates can run programs
+// or use the assembler on their own
+
+pub mod assembler;
+pub mod bus;
+pub mod config;
+pub mod cpu;
+pub mod csr;
+pub mod disassembler;
+pub mod elf;
+pub mod history;
+mod expression;
+pub mod instruction;
+pub mod register;
+mod pseudo;
+pub mod syscall;
+pub mod trap;
+pub mod uart;
+pub mod watch;
+
+pub use assembler::{AssembleError, Assembler};
+pub use bus::{Bus, BusError, MemoryMap, Ram, Rom};
+pub use config::CpuConfig;
+pub use cpu::CPU;
+pub use csr::CsrFile;
+pub use disassembler::Disassembler;
+pub use elf::{Elf, ElfError};
+pub use instruction::{BInstruction, Fields, Format, IInstruction, Instruction, JInstruction, RInstruction, SInstruction, UInstruction};
+pub use syscall::SyscallAbi;
+pub use trap::Trap;
+pub use uart::Uart;
+pub use watch::{WatchHit, WatchKind, Watchpoint};
@@ -1,14 +1,27 @@
+#[cfg(feature = "gui")]
 use macroquad::color::BLACK;
-use macroquad::window::{clear_background, next_frame};
+#[cfg(feature = "gui")]
+use macroquad::window::{clear_background, next_frame, Conf};
+#[cfg(feature = "gui")]
 use crate::app::{update_app, AppState};
 
-mod cpu;
-mod instruction;
-mod assembler;
+#[cfg(feature = "gui")]
 mod app;
+mod cli;
 
-#[macroquad::main("CPU Viewer")]
-async fn main()  {
+// anything on the command line means run headless, otherwise open the window
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() || cfg!(not(feature = "gui")) {
+        std::process::exit(cli::run(&args));
+    }
+
+    #[cfg(feature = "gui")]
+    macroquad::Window::from_config(Conf { window_title: "CPU Viewer".to_string(), ..Default::default() }, gui());
+}
+
+#[cfg(feature = "gui")]
+async fn gui() {
     let mut state = AppState::default();
 
     loop {
@@ -0,0 +1,132 @@
+use crate::expression::split_hi_lo;
+
+// pseudo instructions are just shorthand for one or more real ones, so they get
+// rewritten into normal assembly text before the assembler ever looks at them
+
+// li takes anything that fits in 32 bits, signed or not
+fn li_value(value: i64) -> Option<i32> {
+    (i32::MIN as i64..=u32::MAX as i64).contains(&value).then_some(value as i32)
+}
+
+// size is how many instructions it was given room for, with a label in the value its
+// not known how big it is until later so it always gets both
+fn li_lines(rd: &str, value: i32, size: usize) -> Vec<String> {
+    if size == 1 && (-2048..=2047).contains(&value) {
+        return vec![format!("addi {}, x0, {}", rd, value)];
+    }
+    let (hi, lo) = split_hi_lo(value);
+    if size == 1 {
+        return vec![format!("lui {}, {}", rd, hi)];
+    }
+    vec![format!("lui {}, {}", rd, hi), format!("addi {}, {}, {}", rd, rd, lo)]
+}
+
+// how many instructions li needs for a value
+fn li_size(value: i32) -> usize {
+    if (-2048..=2047).contains(&value) || value & 0xFFF == 0 { 1 } else { 2 }
+}
+
+// how many real instructions a line turns into. has to agree with expand or the labels
+// after it end up in the wrong place. constant gives the value of an operand if it can
+// be worked out yet (it cant if it uses labels)
+pub fn expanded_size(name: &str, operands: &[String], constant: impl Fn(&str) -> Option<i64>) -> usize {
+    match name {
+        "li" => operands.get(1).map(|v| constant(v).and_then(li_value).map(li_size).unwrap_or(2)).unwrap_or(1),
+        "la" | "call" | "tail" => 2,
+        _ => 1,
+    }
+}
+
+// gives back None if name isnt a pseudo instruction so it can be assembled like normal.
+// pc is the address this line starts at, addresses that need to be an offset (la, call, tail)
+// get worked out from it. size is what expanded_size said. errors are the offending text and a message
+pub fn expand(name: &str, operands: &[String], pc: u32, size: usize, value: impl Fn(&str) -> Result<i64, String>) -> Result<Option<Vec<String>>, (String, String)> {
+    let want = |n: usize| -> Result<(), (String, String)> {
+        if operands.len() != n {
+            return Err((name.to_string(), format!("{} expects {} operand(s) but found {}", name, n, operands.len())));
+        }
+        Ok(())
+    };
+    let op = |i: usize| operands[i].as_str();
+
+    // pc relative offset to an address, for the auipc based ones
+    let offset = |text: &str| -> Result<i32, (String, String)> {
+        match value(text) {
+            Ok(address) => Ok((address as i32).wrapping_sub(pc as i32)),
+            Err(e) => Err((text.to_string(), e)),
+        }
+    };
+
+    Ok(Some(match name {
+        "nop" => {
+            want(0)?;
+            vec!["addi x0, x0, 0".to_string()]
+        },
+        "li" => {
+            want(2)?;
+            match value(op(1)) {
+                Ok(v) => match li_value(v) {
+                    Some(v) => li_lines(op(0), v, size),
+                    None => return Err((op(1).to_string(), format!("{} is not a valid 32 bit value", op(1)))),
+                },
+                Err(e) => return Err((op(1).to_string(), e)),
+            }
+        },
+        "la" => {
+            want(2)?;
+            let (hi, lo) = split_hi_lo(offset(op(1))?);
+            vec![format!("auipc {}, {}", op(0), hi), format!("addi {}, {}, {}", op(0), op(0), lo)]
+        },
+        "mv" => { want(2)?; vec![format!("addi {}, {}, 0", op(0), op(1))] },
+        "not" => { want(2)?; vec![format!("xori {}, {}, -1", op(0), op(1))] },
+        "neg" => { want(2)?; vec![format!("sub {}, x0, {}", op(0), op(1))] },
+        "seqz" => { want(2)?; vec![format!("sltiu {}, {}, 1", op(0), op(1))] },
+        "snez" => { want(2)?; vec![format!("sltu {}, x0, {}", op(0), op(1))] },
+        "sltz" => { want(2)?; vec![format!("slt {}, {}, x0", op(0), op(1))] },
+        "sgtz" => { want(2)?; vec![format!("slt {}, x0, {}", op(0), op(1))] },
+
+        // branches against zero
+        "beqz" => { want(2)?; vec![format!("beq {}, x0, {}", op(0), op(1))] },
+        "bnez" => { want(2)?; vec![format!("bne {}, x0, {}", op(0), op(1))] },
+        "blez" => { want(2)?; vec![format!("bge x0, {}, {}", op(0), op(1))] },
+        "bgez" => { want(2)?; vec![format!("bge {}, x0, {}", op(0), op(1))] },
+        "bltz" => { want(2)?; vec![format!("blt {}, x0, {}", op(0), op(1))] },
+        "bgtz" => { want(2)?; vec![format!("blt x0, {}, {}", op(0), op(1))] },
+
+        // the other comparisons are just the real ones with the registers swapped
+        "bgt" => { want(3)?; vec![format!("blt {}, {}, {}", op(1), op(0), op(2))] },
+        "ble" => { want(3)?; vec![format!("bge {}, {}, {}", op(1), op(0), op(2))] },
+        "bgtu" => { want(3)?; vec![format!("bltu {}, {}, {}", op(1), op(0), op(2))] },
+        "bleu" => { want(3)?; vec![format!("bgeu {}, {}, {}", op(1), op(0), op(2))] },
+
+        // jumps
+        "j" => { want(1)?; vec![format!("jal x0, {}", op(0))] },
+        "jr" => { want(1)?; vec![format!("jalr x0, 0({})", op(0))] },
+        "jalr" if operands.len() == 1 && !op(0).contains('(') => vec![format!("jalr x1, 0({})", op(0))],
+        "ret" => { want(0)?; vec!["jalr x0, 0(x1)".to_string()] },
+        "call" => {
+            want(1)?;
+            let (hi, lo) = split_hi_lo(offset(op(0))?);
+            vec![format!("auipc x1, {}", hi), format!("jalr x1, {}(x1)", lo)]
+        },
+        "tail" => {
+            want(1)?;
+            let (hi, lo) = split_hi_lo(offset(op(0))?);
+            vec![format!("auipc x6, {}", hi), format!("jalr x0, {}(x6)", lo)]
+        },
+
+        // csrs
+        "csrr" => { want(2)?; vec![format!("csrrs {}, {}, x0", op(0), op(1))] },
+        "csrw" => { want(2)?; vec![format!("csrrw x0, {}, {}", op(0), op(1))] },
+        "csrs" => { want(2)?; vec![format!("csrrs x0, {}, {}", op(0), op(1))] },
+        "csrc" => { want(2)?; vec![format!("csrrc x0, {}, {}", op(0), op(1))] },
+        "csrwi" => { want(2)?; vec![format!("csrrwi x0, {}, {}", op(0), op(1))] },
+        "csrsi" => { want(2)?; vec![format!("csrrsi x0, {}, {}", op(0), op(1))] },
+        "csrci" => { want(2)?; vec![format!("csrrci x0, {}, {}", op(0), op(1))] },
+        "rdcycle" | "rdtime" | "rdinstret" | "rdcycleh" | "rdtimeh" | "rdinstreth" => {
+            want(1)?;
+            vec![format!("csrrs {}, {}, x0", op(0), &name[2..])]
+        },
+        _ => return Ok(None),
+    }))
+}
@@ -0,0 +1,25 @@
+// the abi names for the 32 integer registers, in register order.
+// x8 is also called fp but s0 is what gets shown
+pub const ABI_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2",
+    "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7",
+    "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+];
+
+// accepts xN or any abi name (including fp)
+pub fn register_number(name: &str) -> Option<u8> {
+    if let Some(digits) = name.strip_prefix('x')
+        && let Ok(n) = digits.parse::<u8>() {
+        // no leading zeros or signs, x05 and x+5 arent registers
+        return (n < 32 && n.to_string() == digits).then_some(n);
+    }
+    if name == "fp" {
+        return Some(8);
+    }
+    ABI_NAMES.iter().position(|n| *n == name).map(|r| r as u8)
+}
+
+pub fn abi_name(register: u8) -> &'static str {
+    ABI_NAMES[(register & 0x1F) as usize]
+}
@@ -0,0 +1,406 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use crate::bus::{Bus, MemoryMap};
+use crate::trap::Trap;
+use crate::uart::Uart;
+
+// which ecalls the cpu answers itself, picked with a7. like rars they get answered whether or not
+// the program has its own trap handler, ones the abi doesnt know (and all of them with None)
+// trap like always. printing and reading goes through the uart's console
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyscallAbi {
+    None,
+    // the rars and venus table, what the course material is written against
+    #[default]
+    Rars,
+    // enough of linux for newlib, so c programs from riscv32-unknown-elf-gcc can run
+    Linux,
+}
+
+impl SyscallAbi {
+    pub const ALL: [SyscallAbi; 3] = [SyscallAbi::None, SyscallAbi::Rars, SyscallAbi::Linux];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyscallAbi::None => "none",
+            SyscallAbi::Rars => "rars",
+            SyscallAbi::Linux => "linux",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SyscallAbi> {
+        SyscallAbi::ALL.into_iter().find(|abi| abi.name() == name)
+    }
+}
+
+// rars numbers
+const PRINT_INT: u32 = 1;
+const PRINT_STRING: u32 = 4;
+const READ_INT: u32 = 5;
+const SBRK: u32 = 9;
+const EXIT: u32 = 10;
+const PRINT_CHAR: u32 = 11;
+const EXIT2: u32 = 17;
+
+// linux numbers, the generic ones riscv uses
+const OPENAT: u32 = 56;
+const CLOSE: u32 = 57;
+const READ: u32 = 63;
+const WRITE: u32 = 64;
+const LINUX_EXIT: u32 = 93;
+const EXIT_GROUP: u32 = 94;
+const BRK: u32 = 214;
+
+const AT_FDCWD: i32 = -100;
+// newlib's open flags, which arent the same as linux's
+const O_ACCMODE: u32 = 0x3;
+const O_RDONLY: u32 = 0x0;
+const O_WRONLY: u32 = 0x1;
+const O_APPEND: u32 = 0x8;
+const O_CREAT: u32 = 0x200;
+const O_TRUNC: u32 = 0x400;
+
+// errors come back in a0 as minus these
+const ENOENT: i32 = 2;
+const EIO: i32 = 5;
+const EBADF: i32 = 9;
+const EACCES: i32 = 13;
+const EFAULT: i32 = 14;
+const EEXIST: i32 = 17;
+const ENOSYS: i32 = 38;
+
+// stdin, stdout and stderr come first
+const FIRST_FILE: u32 = 3;
+// so a missing terminator doesnt read all of memory
+const MAX_STRING: u32 = 4096;
+
+const A0: usize = 10;
+const A7: usize = 17;
+const SP: usize = 2;
+
+// what the cpu should do after an ecall
+pub(crate) enum Outcome {
+    Done,
+    // the input it wants hasnt been typed yet, run the same ecall again
+    Wait,
+    Exit(u32),
+}
+
+// the bits of the cpu an ecall can get at
+struct Call<'a> {
+    registers: &'a mut [u32; 32],
+    bus: &'a mut MemoryMap,
+    uart: Option<&'a Uart>,
+    // every byte written and what was there before (None for ones that cant be looked at),
+    // so the cpu can undo them and check its watchpoints like it does for a store
+    written: &'a mut Vec<(u32, Option<u8>)>,
+}
+
+// errors are the address that couldnt be read or written
+impl Call<'_> {
+    fn arg(&self, n: usize) -> u32 {
+        self.registers[A0 + n]
+    }
+
+    fn ret(&mut self, value: u32) {
+        self.registers[A0] = value;
+    }
+
+    fn read_bytes(&mut self, address: u32, len: u32) -> Result<Vec<u8>, u32> {
+        (0..len).map(|i| {
+            let address = address.wrapping_add(i);
+            self.bus.read_byte(address).map_err(|_| address)
+        }).collect()
+    }
+
+    fn write_bytes(&mut self, address: u32, bytes: &[u8]) -> Result<(), u32> {
+        for (i, byte) in bytes.iter().enumerate() {
+            let address = address.wrapping_add(i as u32);
+            self.written.push((address, self.bus.peek(address)));
+            self.bus.write_byte(address, *byte).map_err(|_| address)?;
+        }
+        Ok(())
+    }
+
+    // up to the 0 at the end, which isnt included
+    fn read_string(&mut self, address: u32) -> Result<Vec<u8>, u32> {
+        let mut string = vec![];
+        for i in 0..MAX_STRING {
+            let address = address.wrapping_add(i);
+            match self.bus.read_byte(address).map_err(|_| address)? {
+                0 => break,
+                byte => string.push(byte),
+            }
+        }
+        Ok(string)
+    }
+
+    // without a uart theres nowhere for it to go
+    fn print(&mut self, bytes: &[u8]) {
+        if let Some(uart) = self.uart {
+            uart.send(bytes);
+        }
+    }
+
+    // and nothing to read, its like the input already ended
+    fn read_line(&mut self, max: usize) -> Option<Vec<u8>> {
+        self.uart.map_or(Some(vec![]), |uart| uart.read_line(max))
+    }
+}
+
+fn io_error(e: std::io::Error) -> i32 {
+    -match e.kind() {
+        ErrorKind::NotFound => ENOENT,
+        ErrorKind::PermissionDenied => EACCES,
+        ErrorKind::AlreadyExists => EEXIST,
+        _ => EIO,
+    }
+}
+
+// symlinks in the sandbox could still lead out of it, so where the path really ends up
+// has to be in there too. a file thats about to be created only has its directory checked
+fn inside(sandbox: &Path, path: &Path) -> Result<PathBuf, i32> {
+    let root = sandbox.canonicalize().map_err(io_error)?;
+    let full = sandbox.join(path);
+    let real = match full.canonicalize() {
+        Ok(real) => real,
+        // a symlink to nowhere is NotFound too but it isnt safe to create through
+        Err(e) if e.kind() == ErrorKind::NotFound && full.symlink_metadata().is_err() => {
+            let (Some(parent), Some(name)) = (full.parent(), full.file_name()) else {
+                return Err(-ENOENT);
+            };
+            parent.canonicalize().map_err(io_error)?.join(name)
+        },
+        Err(e) => return Err(io_error(e)),
+    };
+    if !real.starts_with(&root) {
+        return Err(-EACCES);
+    }
+    Ok(real)
+}
+
+pub(crate) struct Syscalls {
+    abi: SyscallAbi,
+    // the heap starts just past the program and grows up to brk
+    heap_start: u32,
+    brk: u32,
+    // files can only be opened in here, with None there arent any
+    sandbox: Option<PathBuf>,
+    // fd 3 is files[0], closed ones are None so the numbers dont move
+    files: Vec<Option<File>>,
+}
+
+impl Syscalls {
+    pub(crate) fn new(abi: SyscallAbi) -> Syscalls {
+        Syscalls { abi, heap_start: 0, brk: 0, sandbox: None, files: vec![] }
+    }
+
+    // everything opened gets closed and the heap goes back to empty
+    pub(crate) fn reset(&mut self) {
+        self.brk = self.heap_start;
+        self.files.clear();
+    }
+
+    // where the loaded program ends, lined up for anything malloc might put there
+    pub(crate) fn set_heap(&mut self, end: u32) {
+        self.heap_start = end.wrapping_add(7) & !7;
+        self.brk = self.heap_start;
+    }
+
+    pub(crate) fn set_sandbox(&mut self, dir: Option<PathBuf>) {
+        self.sandbox = dir;
+    }
+
+    pub(crate) fn brk(&self) -> u32 {
+        self.brk
+    }
+
+    // putting it back when a step gets undone
+    pub(crate) fn restore_brk(&mut self, brk: u32) {
+        self.brk = brk;
+    }
+
+    pub(crate) fn handle(&mut self, registers: &mut [u32; 32], bus: &mut MemoryMap, uart: Option<&Uart>, written: &mut Vec<(u32, Option<u8>)>) -> Result<Outcome, Trap> {
+        let mut call = Call { registers, bus, uart, written };
+        match self.abi {
+            SyscallAbi::None => Err(Trap::EnvironmentCall),
+            SyscallAbi::Rars => self.rars(&mut call),
+            SyscallAbi::Linux => Ok(self.linux(&mut call)),
+        }
+    }
+
+    // the heap can grow until it runs into the stack or out of memory.
+    // a stack pointer below the heap (like 0 in the tiny layout) isnt in the way
+    fn set_brk(&mut self, brk: u32, call: &Call) -> bool {
+        let sp = call.registers[SP];
+        let size = brk.wrapping_sub(self.heap_start);
+        let fits = brk >= self.heap_start
+            && (sp <= self.heap_start || brk <= sp)
+            && (size == 0 || call.bus.contains(self.heap_start, size));
+        if fits {
+            self.brk = brk;
+        }
+        fits
+    }
+
+    // bad addresses trap like a load would, and numbers rars doesnt have are still a trap
+    fn rars(&mut self, call: &mut Call) -> Result<Outcome, Trap> {
+        match call.registers[A7] {
+            PRINT_INT => call.print((call.arg(0) as i32).to_string().as_bytes()),
+            PRINT_STRING => {
+                let string = call.read_string(call.arg(0)).map_err(Trap::LoadAccessFault)?;
+                call.print(&string);
+            },
+            PRINT_CHAR => call.print(&[call.arg(0) as u8]),
+            // anything that isnt a number reads as 0
+            READ_INT => {
+                let Some(line) = call.read_line(usize::MAX) else {
+                    return Ok(Outcome::Wait);
+                };
+                call.ret(String::from_utf8_lossy(&line).trim().parse::<i32>().unwrap_or(0) as u32);
+            },
+            // gives back the old break, or -1 if theres no room
+            SBRK => {
+                let old = self.brk;
+                let moved = self.set_brk(old.wrapping_add(call.arg(0)), call);
+                call.ret(if moved { old } else { u32::MAX });
+            },
+            EXIT => return Ok(Outcome::Exit(0)),
+            EXIT2 => return Ok(Outcome::Exit(call.arg(0))),
+            _ => return Err(Trap::EnvironmentCall),
+        }
+        Ok(Outcome::Done)
+    }
+
+    // never traps, things go wrong the linux way with a negative errno in a0
+    fn linux(&mut self, call: &mut Call) -> Outcome {
+        let result = match call.registers[A7] {
+            WRITE => self.write(call),
+            READ => match self.read(call) {
+                Some(result) => result,
+                None => return Outcome::Wait,
+            },
+            OPENAT => self.openat(call),
+            CLOSE => self.close(call.arg(0)),
+            // brk(0) is how the current break gets asked for, a bad one leaves it where it is
+            BRK => {
+                if call.arg(0) != 0 {
+                    self.set_brk(call.arg(0), call);
+                }
+                self.brk as i32
+            },
+            LINUX_EXIT | EXIT_GROUP => return Outcome::Exit(call.arg(0)),
+            _ => -ENOSYS,
+        };
+        call.ret(result as u32);
+        Outcome::Done
+    }
+
+    fn file(&mut self, fd: u32) -> Option<&mut File> {
+        self.files.get_mut(fd.checked_sub(FIRST_FILE)? as usize)?.as_mut()
+    }
+
+    fn write(&mut self, call: &mut Call) -> i32 {
+        let (fd, count) = (call.arg(0), call.arg(2));
+        let Ok(bytes) = call.read_bytes(call.arg(1), count) else {
+            return -EFAULT;
+        };
+        match fd {
+            1 | 2 if call.uart.is_some() => call.print(&bytes),
+            _ => match self.file(fd) {
+                Some(file) => {
+                    if let Err(e) = file.write_all(&bytes) {
+                        return io_error(e);
+                    }
+                },
+                None => return -EBADF,
+            },
+        }
+        count as i32
+    }
+
+    // None when its waiting on a line to be typed
+    fn read(&mut self, call: &mut Call) -> Option<i32> {
+        let (fd, count) = (call.arg(0), call.arg(2));
+        let bytes = if fd == 0 {
+            call.read_line(count as usize)?
+        } else {
+            let Some(file) = self.file(fd) else {
+                return Some(-EBADF);
+            };
+            let mut bytes = vec![];
+            if let Err(e) = file.take(count as u64).read_to_end(&mut bytes) {
+                return Some(io_error(e));
+            }
+            bytes
+        };
+        Some(match call.write_bytes(call.arg(1), &bytes) {
+            Ok(()) => bytes.len() as i32,
+            Err(_) => -EFAULT,
+        })
+    }
+
+    // only paths inside the sandbox, relative to it, with no way of climbing out
+    fn openat(&mut self, call: &mut Call) -> i32 {
+        if call.arg(0) as i32 != AT_FDCWD {
+            return -EBADF;
+        }
+        let Ok(path) = call.read_string(call.arg(1)) else {
+            return -EFAULT;
+        };
+        let Some(sandbox) = &self.sandbox else {
+            return -EACCES;
+        };
+        let path = String::from_utf8_lossy(&path).into_owned();
+        let path = Path::new(&path);
+        if path.as_os_str().is_empty() {
+            return -ENOENT;
+        }
+        if !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            return -EACCES;
+        }
+
+        let path = match inside(sandbox, path) {
+            Ok(path) => path,
+            Err(e) => return e,
+        };
+
+        let flags = call.arg(2);
+        let access = flags & O_ACCMODE;
+        let file = OpenOptions::new()
+            .read(access != O_WRONLY)
+            .write(access != O_RDONLY)
+            .append(flags & O_APPEND != 0)
+            .create(flags & O_CREAT != 0)
+            .truncate(flags & O_TRUNC != 0)
+            .open(path);
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => return io_error(e),
+        };
+        let slot = match self.files.iter().position(|f| f.is_none()) {
+            Some(slot) => slot,
+            None => {
+                self.files.push(None);
+                self.files.len() - 1
+            },
+        };
+        self.files[slot] = Some(file);
+        (slot as u32 + FIRST_FILE) as i32
+    }
+
+    // closing stdin, stdout or stderr is allowed but doesnt do anything
+    fn close(&mut self, fd: u32) -> i32 {
+        if fd < FIRST_FILE {
+            return 0;
+        }
+        match self.files.get_mut((fd - FIRST_FILE) as usize) {
+            Some(file @ Some(_)) => {
+                *file = None;
+                0
+            },
+            _ => -EBADF,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt::{Display, Formatter};
+
+// everything that can stop an instruction from finishing normally.
+// the value inside is what ends up in mtval (the bad address or instruction)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trap {
+    InstructionAddressMisaligned(u32),
+    InstructionAccessFault(u32),
+    IllegalInstruction(u32),
+    Breakpoint(u32),
+    LoadAddressMisaligned(u32),
+    LoadAccessFault(u32),
+    StoreAddressMisaligned(u32),
+    StoreAccessFault(u32),
+    EnvironmentCall,
+}
+
+impl Trap {
+    // exception codes from the privileged spec (ecall is the one from m mode)
+    pub fn cause(&self) -> u32 {
+        match self {
+            Trap::InstructionAddressMisaligned(_) => 0,
+            Trap::InstructionAccessFault(_) => 1,
+            Trap::IllegalInstruction(_) => 2,
+            Trap::Breakpoint(_) => 3,
+            Trap::LoadAddressMisaligned(_) => 4,
+            Trap::LoadAccessFault(_) => 5,
+            Trap::StoreAddressMisaligned(_) => 6,
+            Trap::StoreAccessFault(_) => 7,
+            Trap::EnvironmentCall => 11,
+        }
+    }
+
+    pub fn tval(&self) -> u32 {
+        match self {
+            Trap::InstructionAddressMisaligned(v)
+            | Trap::InstructionAccessFault(v)
+            | Trap::IllegalInstruction(v)
+            | Trap::Breakpoint(v)
+            | Trap::LoadAddressMisaligned(v)
+            | Trap::LoadAccessFault(v)
+            | Trap::StoreAddressMisaligned(v)
+            | Trap::StoreAccessFault(v) => *v,
+            Trap::EnvironmentCall => 0,
+        }
+    }
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trap::InstructionAddressMisaligned(a) => write!(f, "Instruction address misaligned (0x{:x})", a),
+            Trap::InstructionAccessFault(a) => write!(f, "Instruction access fault (0x{:x})", a),
+            Trap::IllegalInstruction(i) => write!(f, "Illegal instruction (0x{:08x})", i),
+            Trap::Breakpoint(a) => write!(f, "Breakpoint (0x{:x})", a),
+            Trap::LoadAddressMisaligned(a) => write!(f, "Load address misaligned (0x{:x})", a),
+            Trap::LoadAccessFault(a) => write!(f, "Load access fault (0x{:x})", a),
+            Trap::StoreAddressMisaligned(a) => write!(f, "Store address misaligned (0x{:x})", a),
+            Trap::StoreAccessFault(a) => write!(f, "Store access fault (0x{:x})", a),
+            Trap::EnvironmentCall => write!(f, "Environment call"),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use crate::bus::{Bus, BusError};
+
+// where the uart goes unless the config says otherwise, same as qemu's virt machine
+pub const UART_BASE: u32 = 0x1000_0000;
+// eight byte wide registers one after the other
+pub const UART_SIZE: u32 = 8;
+
+// register offsets. some share one and which you get depends on reading or writing,
+// or on the dlab bit in lcr which swaps in the baud rate divisor
+const RBR_THR_DLL: u32 = 0;
+const IER_DLM: u32 = 1;
+const IIR_FCR: u32 = 2;
+const LCR: u32 = 3;
+const MCR: u32 = 4;
+const LSR: u32 = 5;
+const MSR: u32 = 6;
+const SCR: u32 = 7;
+
+const LCR_DLAB: u8 = 0x80;
+const LSR_DATA_READY: u8 = 0x01;
+// bytes go out as soon as theyre written so theres always room for more
+const LSR_THR_EMPTY: u8 = 0x20;
+const LSR_TRANSMITTER_EMPTY: u8 = 0x40;
+const IIR_NO_INTERRUPT: u8 = 0x01;
+const IIR_FIFO_ENABLED: u8 = 0xC0;
+const FCR_ENABLE: u8 = 0x01;
+const FCR_CLEAR_RX: u8 = 0x02;
+// clear to send, data set ready and carrier detect, like something is plugged in
+const MSR_CONNECTED: u8 = 0xB0;
+
+#[derive(Default)]
+struct State {
+    // typed bytes the program hasnt read yet. a real receive fifo is 16 bytes but this
+    // never overruns, its like the other end waits until theres room
+    input: VecDeque<u8>,
+    // where more input comes from once thats empty, dropped once it runs out. lsr says
+    // theres data while its still around and only reading rbr waits on it, so a program
+    // that just checks lsr before sending never gets stuck
+    source: Option<Box<dyn FnMut() -> Option<u8>>>,
+    // the source ran out, theres never going to be any more input
+    ended: bool,
+    // bytes the program sent that havent been shown yet
+    output: Vec<u8>,
+    ier: u8,
+    lcr: u8,
+    mcr: u8,
+    scr: u8,
+    divisor: [u8; 2],
+    fifo: bool,
+}
+
+impl State {
+    fn dlab(&self) -> bool {
+        self.lcr & LCR_DLAB != 0
+    }
+
+    fn fill_input(&mut self) {
+        if !self.input.is_empty() {
+            return;
+        }
+        match self.source.as_mut().map(|source| source()) {
+            Some(Some(byte)) => self.input.push_back(byte),
+            Some(None) => self.end_input(),
+            None => (),
+        }
+    }
+
+    fn end_input(&mut self) {
+        self.source = None;
+        self.ended = true;
+    }
+
+    // every register except rbr can be read without changing anything
+    fn register(&self, offset: u32) -> Option<u8> {
+        Some(match offset {
+            RBR_THR_DLL if self.dlab() => self.divisor[0],
+            RBR_THR_DLL => return None,
+            IER_DLM if self.dlab() => self.divisor[1],
+            IER_DLM => self.ier,
+            IIR_FCR => IIR_NO_INTERRUPT | if self.fifo { IIR_FIFO_ENABLED } else { 0 },
+            LCR => self.lcr,
+            MCR => self.mcr,
+            LSR => {
+                let ready = !self.input.is_empty() || self.source.is_some();
+                LSR_THR_EMPTY | LSR_TRANSMITTER_EMPTY | if ready { LSR_DATA_READY } else { 0 }
+            },
+            MSR => MSR_CONNECTED,
+            SCR => self.scr,
+            _ => return None,
+        })
+    }
+}
+
+// a 16550 without any of the waiting, programs write bytes to thr and read typed ones from
+// rbr once lsr says theres data ready. theres no interrupts since the cpu doesnt take
+// external ones, so programs have to poll.
+// clones are the same uart, the cpu puts one on the bus and keeps one to get at the bytes
+#[derive(Clone, Default)]
+pub struct Uart {
+    state: Rc<RefCell<State>>,
+}
+
+impl Uart {
+    pub fn new() -> Uart {
+        Uart::default()
+    }
+
+    // everything the program sent since the last time this was called
+    pub fn take_output(&self) -> Vec<u8> {
+        std::mem::take(&mut self.state.borrow_mut().output)
+    }
+
+    // like the bytes were typed at the other end
+    pub fn receive(&self, bytes: &[u8]) {
+        self.state.borrow_mut().input.extend(bytes);
+    }
+
+    // ecalls print straight to the other end without going through thr
+    pub(crate) fn send(&self, bytes: &[u8]) {
+        self.state.borrow_mut().output.extend_from_slice(bytes);
+    }
+
+    // up to max bytes of the next line, newline included. once the source has run out
+    // its whatever is left even if thats nothing. None if a whole line hasnt been typed yet
+    pub(crate) fn read_line(&self, max: usize) -> Option<Vec<u8>> {
+        let mut state = self.state.borrow_mut();
+        let end = loop {
+            if let Some(newline) = state.input.iter().position(|b| *b == b'\n') {
+                break newline + 1;
+            }
+            match state.source.as_mut().map(|source| source()) {
+                Some(Some(byte)) => state.input.push_back(byte),
+                Some(None) => state.end_input(),
+                None if state.ended => break state.input.len(),
+                None => return None,
+            }
+        };
+        Some(state.input.drain(..end.min(max)).collect())
+    }
+
+    // for input that isnt all there up front, like stdin
+    pub fn set_input(&self, source: impl FnMut() -> Option<u8> + 'static) {
+        self.state.borrow_mut().source = Some(Box::new(source));
+    }
+
+    // back to how it was at power on, bytes that havent been read or taken are dropped.
+    // the input source stays
+    pub fn reset(&self) {
+        let mut state = self.state.borrow_mut();
+        let source = state.source.take();
+        *state = State { source, ..State::default() };
+    }
+}
+
+impl Bus for Uart {
+    fn read_byte(&mut self, address: u32) -> Result<u8, BusError> {
+        let mut state = self.state.borrow_mut();
+        if address == RBR_THR_DLL && !state.dlab() {
+            state.fill_input();
+            // nothing typed reads as 0, programs should check lsr first
+            return Ok(state.input.pop_front().unwrap_or(0));
+        }
+        state.register(address).ok_or(BusError::Unmapped(address))
+    }
+
+    fn write_byte(&mut self, address: u32, value: u8) -> Result<(), BusError> {
+        let mut state = self.state.borrow_mut();
+        match address {
+            RBR_THR_DLL if state.dlab() => state.divisor[0] = value,
+            RBR_THR_DLL => state.output.push(value),
+            IER_DLM if state.dlab() => state.divisor[1] = value,
+            IER_DLM => state.ier = value & 0x0F,
+            IIR_FCR => {
+                state.fifo = value & FCR_ENABLE != 0;
+                if value & FCR_CLEAR_RX != 0 {
+                    state.input.clear();
+                }
+            },
+            LCR => state.lcr = value,
+            MCR => state.mcr = value & 0x1F,
+            // the status registers are read only, writing them does nothing
+            LSR | MSR => (),
+            SCR => state.scr = value,
+            _ => return Err(BusError::Unmapped(address)),
+        }
+        Ok(())
+    }
+
+    fn peek(&self, address: u32) -> Option<u8> {
+        self.state.borrow().register(address)
+    }
+}
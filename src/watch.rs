@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter};
+
+// what sort of access sets a watchpoint off. change is a write that leaves a different
+// value than was there before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Change,
+}
+
+impl WatchKind {
+    pub const ALL: [WatchKind; 3] = [WatchKind::Read, WatchKind::Write, WatchKind::Change];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WatchKind::Read => "read",
+            WatchKind::Write => "write",
+            WatchKind::Change => "change",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<WatchKind> {
+        WatchKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+// len bytes starting at address, only loads and stores the program does count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub address: u32,
+    pub len: u32,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    pub fn new(address: u32, len: u32, kind: WatchKind) -> Watchpoint {
+        Watchpoint { address, len, kind }
+    }
+
+    // whether any of the size bytes at address are watched
+    pub fn covers(&self, address: u32, size: u32) -> bool {
+        let end = self.address as u64 + self.len as u64;
+        (address as u64) < end && address as u64 + size as u64 > self.address as u64
+    }
+
+    // whether a store of size bytes at address left any of the watched ones different
+    pub fn changed(&self, address: u32, size: u32, old: u32, new: u32) -> bool {
+        (0..size).any(|i| {
+            let shift = i * 8;
+            self.covers(address.wrapping_add(i), 1) && (old >> shift) as u8 != (new >> shift) as u8
+        })
+    }
+}
+
+// the access that set a watchpoint off. reads have the same old and new value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub watchpoint: Watchpoint,
+    // the load or store that did it
+    pub pc: u32,
+    pub address: u32,
+    pub size: u32,
+    pub old: u32,
+    pub new: u32,
+}
+
+impl Display for WatchHit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let access = if self.watchpoint.kind == WatchKind::Read { "read" } else { "write" };
+        write!(f, "{} of {} byte(s) at 0x{:x} by pc 0x{:x}: 0x{:x} -> 0x{:x}", access, self.size, self.address, self.pc, self.old, self.new)
+    }
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{errors, words};
+use riscvemulator::Instruction;
+
+// the offset a branch or jump word ended up with
+fn offset(word: u32) -> i32 {
+    Instruction::decode(word).unwrap().fields().imm
+}
+
+#[test]
+fn branch_offsets_get_13_bits() {
+    assert_eq!(offset(words("beq x0, x0, 3000")[0]), 3000);
+    assert_eq!(offset(words("bne a0, a1, -4096")[0]), -4096);
+    assert_eq!(offset(words("blt a0, a1, 4094")[0]), 4094);
+
+    // a label more than 2KiB away
+    let source = format!("beqz a0, far\n{}far: ebreak", "nop\n".repeat(1000));
+    assert_eq!(offset(words(&source)[0]), 4004);
+    let source = format!("back: nop\n{}bgeu a0, a1, back", "nop\n".repeat(1000));
+    assert_eq!(offset(words(&source)[1001]), -4004);
+
+    assert_eq!(errors("beq x0, x0, 4096"), ["4096 is not a valid branch offset, it has to be even and fit in 13 bits"]);
+    assert_eq!(errors("beq x0, x0, -4098").len(), 1);
+    // odd offsets used to get rounded down
+    assert_eq!(errors("beq x0, x0, 3"), ["3 is not a valid branch offset, it has to be even and fit in 13 bits"]);
+    assert_eq!(errors("beq x0, 8").len(), 1);
+}
+
+#[test]
+fn jump_offsets_get_21_bits() {
+    assert_eq!(offset(words("jal x0, 0xFFFFE")[0]), 0xFFFFE);
+    assert_eq!(offset(words("jal -0x100000")[0]), -0x100000);
+    assert_eq!(errors("jal x0, 0x100000").len(), 1);
+    assert_eq!(errors("jal x0, 5").len(), 1);
+}
+
+#[test]
+fn system_instructions_take_no_operands() {
+    assert_eq!(words("ecall\nebreak\nmret"), [0x0000_0073, 0x0010_0073, 0x3020_0073]);
+    assert_eq!(errors("ebreak x1"), ["ebreak expects 0 operand(s) but found 1"]);
+    assert_eq!(errors("ecall a0, a1"), ["ecall expects 0 operand(s) but found 2"]);
+    assert_eq!(errors("mret 0").len(), 1);
+}
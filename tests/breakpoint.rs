@@ -0,0 +1,75 @@
+mod common;
+
+use common::load;
+
+const LOOP: &str = "
+    li t0, 10000
+    li t1, 0
+loop:
+    addi t1, t1, 1
+    addi t0, t0, -1
+    bnez t0, loop
+    # done
+    li a0, 1
+    ebreak
+";
+
+#[test]
+fn source_lines_and_addresses() {
+    let (assembler, _) = load(LOOP);
+    // li 10000 is two words
+    assert_eq!(assembler.address_of_line(2), Some(0x100));
+    assert_eq!(assembler.address_of_line(3), Some(0x108));
+    assert_eq!(assembler.address_of_line(5), Some(0x10C));
+    // blank, label only and comment lines dont have any code
+    assert_eq!(assembler.address_of_line(1), None);
+    assert_eq!(assembler.address_of_line(4), None);
+    assert_eq!(assembler.address_of_line(8), None);
+
+    assert_eq!(assembler.line_of_address(0x104), Some(2));
+    assert_eq!(assembler.line_of_address(0x114), Some(7));
+    assert_eq!(assembler.line_of_address(0x106), None);
+    assert_eq!(assembler.line_of_address(0x200), None);
+    assert_eq!(assembler.view_source()[3], "loop:");
+}
+
+#[test]
+fn run_stops_at_breakpoints() {
+    let (assembler, mut cpu) = load(LOOP);
+    let body = assembler.address_of_line(6).unwrap();
+    cpu.set_breakpoint(body);
+
+    assert_eq!(cpu.run(u64::MAX), 4);
+    assert!(cpu.at_breakpoint());
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.view_registers()[6], 1);
+
+    // starting on one runs past it, round the loop once more
+    assert_eq!(cpu.run(u64::MAX), 3);
+    assert_eq!(cpu.get_pc(), body);
+    assert_eq!(cpu.view_registers()[6], 2);
+
+    // breakpoints stay through a reset
+    cpu.reset();
+    cpu.run(u64::MAX);
+    assert_eq!(cpu.get_pc(), body);
+    assert_eq!(cpu.view_registers()[6], 1);
+
+    cpu.remove_breakpoint(body);
+    cpu.set_breakpoint(assembler.address_of_line(9).unwrap());
+    cpu.run(u64::MAX);
+    assert!(cpu.at_breakpoint());
+    assert_eq!(cpu.view_registers()[6], 10000);
+    assert_eq!(cpu.view_registers()[10], 0);
+    assert_eq!(cpu.view_breakpoints().len(), 1);
+}
+
+#[test]
+fn limit_still_counts() {
+    let (_, mut cpu) = load(LOOP);
+    assert_eq!(cpu.run(100), 100);
+    assert!(!cpu.is_halted());
+    cpu.run(u64::MAX);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_registers()[10], 1);
+}
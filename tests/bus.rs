@@ -0,0 +1,135 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use riscvemulator::{Assembler, Bus, BusError, MemoryMap, Ram, Rom, Trap, CPU};
+
+fn run(cpu: &mut CPU, source: &str) {
+    cpu.load_program(&Assembler::from_source(source).assemble_bytes().unwrap()).unwrap();
+    cpu.run(1000);
+}
+
+// counts up every time its read, and remembers the last thing written to it
+struct Counter {
+    count: u8,
+    written: Rc<Cell<u8>>,
+}
+
+impl Bus for Counter {
+    fn read_byte(&mut self, address: u32) -> Result<u8, BusError> {
+        if address != 0 {
+            return Err(BusError::Unmapped(address));
+        }
+        self.count += 1;
+        Ok(self.count)
+    }
+
+    fn write_byte(&mut self, address: u32, value: u8) -> Result<(), BusError> {
+        if address != 0 {
+            return Err(BusError::Denied(address));
+        }
+        self.written.set(value);
+        Ok(())
+    }
+
+    fn peek(&self, _address: u32) -> Option<u8> {
+        None
+    }
+}
+
+#[test]
+fn accesses_go_to_the_right_region() {
+    let mut map = MemoryMap::new();
+    map.attach("low", 0x0, 0x100, Box::new(Ram::new(0x100))).unwrap();
+    map.attach("high", 0x1000, 0x100, Box::new(Ram::new(0x100))).unwrap();
+
+    map.write_word(0x10, 0x1234_5678).unwrap();
+    map.write_half(0x1010, 0xBEEF).unwrap();
+    assert_eq!(map.read_word(0x10), Ok(0x1234_5678));
+    assert_eq!(map.read_byte(0x11), Ok(0x56));
+    assert_eq!(map.read_half(0x1010), Ok(0xBEEF));
+    assert_eq!(map.peek(0x1011), Some(0xBE));
+    // same offset in the other region wasnt touched
+    assert_eq!(map.read_half(0x10), Ok(0x5678));
+
+    assert_eq!(map.read_byte(0x800), Err(BusError::Unmapped(0x800)));
+    // has to fit in one region
+    assert_eq!(map.read_word(0xFE), Err(BusError::Unmapped(0xFE)));
+    assert_eq!(map.peek(0x800), None);
+}
+
+#[test]
+fn regions_cant_overlap() {
+    let mut map = MemoryMap::new();
+    map.attach("ram", 0x1000, 0x1000, Box::new(Ram::new(0x1000))).unwrap();
+    assert!(map.attach("rom", 0x1FFC, 0x10, Box::new(Rom::new(0x10))).is_err());
+    assert!(map.attach("rom", 0x0, 0x1001, Box::new(Rom::new(0x1001))).is_err());
+    assert!(map.attach("rom", 0xFFFF_FF00, 0x200, Box::new(Rom::new(0x200))).is_err());
+    assert!(map.attach("rom", 0xFFFF_FF00, 0x100, Box::new(Rom::new(0x100))).is_ok());
+    assert!(map.attach("rom", 0x0, 0x1000, Box::new(Rom::new(0x1000))).is_ok());
+
+    let regions = map.regions().map(|(_, base, size)| (base, size)).collect::<Vec<_>>();
+    assert_eq!(regions, vec![(0x0, 0x1000), (0x1000, 0x1000), (0xFFFF_FF00, 0x100)]);
+}
+
+#[test]
+fn rom_can_be_loaded_and_read_but_not_written() {
+    let mut rom = Rom::new(8);
+    rom.load(0, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    assert_eq!(rom.read_word(4), Ok(0x0807_0605));
+    assert_eq!(rom.write_byte(2, 0), Err(BusError::Denied(2)));
+    assert_eq!(rom.read_byte(2), Ok(3));
+}
+
+#[test]
+fn writing_rom_is_a_store_access_fault() {
+    let mut cpu = CPU::default();
+    let mut rom = Rom::new(0x100);
+    rom.load(0, &0x55u32.to_le_bytes()).unwrap();
+    cpu.attach("rom", 0x1000, 0x100, Box::new(rom)).unwrap();
+    run(&mut cpu, "
+        li t0, 0x1000
+        lw a0, 0(t0)
+        sw a0, 4(t0)
+        ebreak
+    ");
+    assert_eq!(cpu.view_registers()[10], 0x55);
+    // the fault has the full address, not where it is in the rom
+    assert_eq!(cpu.view_fault(), Some(&Trap::StoreAccessFault(0x1004)));
+}
+
+#[test]
+fn code_runs_from_rom() {
+    let mut rom = Rom::new(0x100);
+    rom.load(0, &Assembler::from_source("li a0, 42\nebreak").assemble_bytes().unwrap()).unwrap();
+    let mut cpu = CPU::default();
+    cpu.attach("rom", 0x1000, 0x100, Box::new(rom)).unwrap();
+    run(&mut cpu, "
+        li t0, 0x1000
+        jr t0
+    ");
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_registers()[10], 42);
+}
+
+#[test]
+fn devices_see_every_access() {
+    let written = Rc::new(Cell::new(0));
+    let mut cpu = CPU::default();
+    cpu.attach("counter", 0x2000, 4, Box::new(Counter { count: 0, written: written.clone() })).unwrap();
+    run(&mut cpu, "
+        li t0, 0x2000
+        lbu a0, 0(t0)
+        lbu a0, 0(t0)
+        li t1, 99
+        sb t1, 0(t0)
+        lbu a1, 1(t0)
+        ebreak
+    ");
+    assert_eq!(cpu.view_registers()[10], 2);
+    assert_eq!(written.get(), 99);
+    // the device said no to offset 1
+    assert_eq!(cpu.view_fault(), Some(&Trap::LoadAccessFault(0x2001)));
+    // showing memory doesnt read the device
+    assert_eq!(cpu.view_byte(0x2000), None);
+}
@@ -0,0 +1,53 @@
+// runs the built binary headless like a script would
+use std::fs;
+use std::process::{Command, Stdio};
+
+// the exit status for a program written out to a temporary file
+fn status(name: &str, source: &str, args: &[&str]) -> Option<i32> {
+    let file = std::env::temp_dir().join(format!("riscvemulator-cli-{}-{}.rv", name, std::process::id()));
+    fs::write(&file, source).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_riscvemulator"))
+        .arg(&file)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    fs::remove_file(&file).unwrap();
+    status.code()
+}
+
+#[test]
+fn exit_codes_come_back_out() {
+    assert_eq!(status("exit4", "li a0, 4\nli a7, 17\necall", &[]), Some(4));
+    assert_eq!(status("exit0", "li a0, 0\nli a7, 17\necall", &[]), Some(0));
+    // only the low 8 bits, like a real process
+    assert_eq!(status("exit256", "li a0, 257\nli a7, 17\necall", &[]), Some(1));
+    // the emulator's own codes arent ones a program can give back
+    assert_eq!(status("exit121", "li a0, 121\nli a7, 17\necall", &[]), Some(120));
+    assert_eq!(status("exit125", "li a0, 125\nli a7, 17\necall", &[]), Some(120));
+    assert_eq!(status("exit126", "li a0, 126\nli a7, 17\necall", &[]), Some(126));
+    assert_eq!(status("linux", "li a0, 3\nli a7, 93\necall", &["--syscalls", "linux"]), Some(3));
+    // rars exit is always 0
+    assert_eq!(status("rars", "li a0, 3\nli a7, 10\necall", &[]), Some(0));
+    assert_eq!(status("ebreak", "ebreak", &[]), Some(0));
+}
+
+#[test]
+fn programs_too_big_for_memory() {
+    // the default layout only has 0x100 bytes past the code
+    assert_eq!(status("too-big", "ebreak\n.data\n.space 0x200\n.word 1", &[]), Some(123));
+    assert_eq!(status("fits", "ebreak\n.data\n.word 1", &[]), Some(0));
+}
+
+#[test]
+fn emulator_codes_stay_apart() {
+    assert_eq!(status("fault", "lw a0, 1(x0)", &[]), Some(121));
+    assert_eq!(status("watch", "li t0, 0x180\nsw t0, 0(t0)\nebreak", &["--watch", "0x180:4"]), Some(122));
+    assert_eq!(status("assemble", "nope", &[]), Some(123));
+    assert_eq!(status("limit", "loop: j loop", &["--limit", "100"]), Some(124));
+    assert_eq!(status("usage", "ebreak", &["--preset", "huge"]), Some(125));
+    // a program exiting with 1 isnt a fault
+    assert_eq!(status("exit1", "li a0, 1\nli a7, 17\necall", &[]), Some(1));
+}
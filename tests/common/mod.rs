@@ -0,0 +1,31 @@
+// helpers the test files share, not every file uses all of them
+#![allow(dead_code)]
+
+use riscvemulator::{Assembler, CPU};
+
+// assembled at the default reset vector and loaded into a fresh cpu, the assembler
+// is handed back too for its labels and line numbers
+pub fn load(source: &str) -> (Assembler, CPU) {
+    let assembler = Assembler::from_source(source);
+    let mut cpu = CPU::default();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+    (assembler, cpu)
+}
+
+// loaded and run until it stops
+pub fn run(source: &str) -> CPU {
+    let (_, mut cpu) = load(source);
+    cpu.run(10_000);
+    cpu
+}
+
+// the words source assembles to at the default base
+pub fn words(source: &str) -> Vec<u32> {
+    Assembler::from_source(source).assemble().unwrap()
+}
+
+// just the messages of whatever went wrong, empty when it assembled
+pub fn errors(source: &str) -> Vec<String> {
+    Assembler::from_source(source).assemble().err().unwrap_or_default()
+        .into_iter().map(|e| e.message).collect()
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::run;
+
+// runs op on a and b and gives back rd, b is the immediate for the i ones
+fn op(name: &str, a: i32, b: i32) -> u32 {
+    let operand = if name.starts_with("slti") { b.to_string() } else { "a1".to_string() };
+    let cpu = run(&format!("
+        li a0, {}
+        li a1, {}
+        {} a2, a0, {}
+        ebreak
+    ", a, b, name, operand));
+    assert_eq!(cpu.view_fault(), None);
+    cpu.view_registers()[12]
+}
+
+// 1 if the branch was taken
+fn branch(name: &str, a: i32, b: i32) -> u32 {
+    let cpu = run(&format!("
+        li a0, {}
+        li a1, {}
+        {} a0, a1, taken
+        ebreak
+        taken:
+        li a2, 1
+        ebreak
+    ", a, b, name));
+    assert_eq!(cpu.view_fault(), None);
+    cpu.view_registers()[12]
+}
+
+#[test]
+fn set_less_than() {
+    // -1 is the biggest there is unsigned
+    assert_eq!(op("slt", -1, 1), 1);
+    assert_eq!(op("sltu", -1, 1), 0);
+    assert_eq!(op("slt", 1, -1), 0);
+    assert_eq!(op("sltu", 1, -1), 1);
+    assert_eq!(op("slt", i32::MIN, i32::MAX), 1);
+    assert_eq!(op("sltu", i32::MIN, i32::MAX), 0);
+    assert_eq!(op("slt", -5, -5), 0);
+
+    // the immediate is sign extended for both, so sltiu against -1 is everything but -1
+    assert_eq!(op("slti", -2, -1), 1);
+    assert_eq!(op("sltiu", -2, -1), 1);
+    assert_eq!(op("sltiu", -1, -1), 0);
+    assert_eq!(op("slti", 0, -1), 0);
+    assert_eq!(op("sltiu", 0, -1), 1);
+    assert_eq!(op("sltiu", 0, 1), 1);
+}
+
+#[test]
+fn signed_and_unsigned_branches() {
+    assert_eq!(["blt", "bltu", "bge", "bgeu"].map(|name| branch(name, -1, 1)), [1, 0, 0, 1]);
+    assert_eq!(["blt", "bltu", "bge", "bgeu"].map(|name| branch(name, 1, -1)), [0, 1, 1, 0]);
+    assert_eq!(["blt", "bltu", "bge", "bgeu"].map(|name| branch(name, -3, -3)), [0, 0, 1, 1]);
+    assert_eq!(branch("blt", i32::MIN, i32::MAX), 1);
+    assert_eq!(branch("bltu", i32::MIN, i32::MAX), 0);
+}
+
+#[test]
+fn counting_down_through_zero() {
+    // goes round with 2, 1, 0, -1 and -2 then stops at -3
+    let cpu = run("
+        li a0, 2
+        li a1, -2
+        loop:
+        addi a2, a2, 1
+        addi a0, a0, -1
+        bge a0, a1, loop
+        ebreak
+    ");
+    assert_eq!(cpu.view_registers()[12], 5);
+    assert_eq!(cpu.view_registers()[10], -3i32 as u32);
+}
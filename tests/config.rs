@@ -0,0 +1,129 @@
+use riscvemulator::{Assembler, CpuConfig, CPU};
+
+// assembles for wherever the config puts code and runs it to the end
+fn run(config: CpuConfig, source: &str) -> CPU {
+    let mut assembler = Assembler::from_source(source);
+    assembler.set_base(config.reset_vector);
+    let mut cpu = CPU::new(config).unwrap();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+    cpu.run(1000);
+    cpu
+}
+
+#[test]
+fn presets_are_valid() {
+    for (name, config) in CpuConfig::presets() {
+        assert_eq!(config.check(), Ok(()), "{}", name);
+        assert_eq!(CpuConfig::preset(name), Some(config));
+    }
+    assert_eq!(CpuConfig::preset("huge"), None);
+}
+
+#[test]
+fn bad_layouts_are_caught() {
+    let config = CpuConfig::default();
+    assert!(config.ram_size(0).check().is_err());
+    assert!(config.ram_size(0x201).check().is_err());
+    assert!(config.ram_base(0xFFFF_0000).ram_size(0x20000).check().is_err());
+    assert!(config.reset_vector(0x400).check().is_err());
+
+    // and no cpu gets made with one, instead of one missing its ram or uart
+    assert_eq!(CPU::new(config.reset_vector(0x400)).err(), Some("reset vector 0x400 is outside of ram".to_string()));
+    assert!(CPU::new(config.uart(Some(0x100))).is_err());
+    assert!(CPU::new(config.ram_size(0)).is_err());
+}
+
+#[test]
+fn starts_at_the_reset_vector_with_the_stack() {
+    let config = CpuConfig::preset("virt").unwrap();
+    let cpu = CPU::new(config).unwrap();
+    assert_eq!(cpu.get_pc(), 0x8000_0000);
+    assert_eq!(cpu.view_registers()[2], 0x8000_0000 + 128 * 1024 * 1024);
+    let regions = cpu.view_bus().regions().collect::<Vec<_>>();
+    assert_eq!(regions, vec![("uart", 0x1000_0000, 8), ("ram", 0x8000_0000, 128 * 1024 * 1024)]);
+}
+
+#[test]
+fn stack_at_the_top_of_big_ram() {
+    let cpu = run(CpuConfig::preset("virt").unwrap(), "
+        la t0, value
+        lw t1, 0(t0)
+        addi sp, sp, -4
+        sw t1, 0(sp)
+        lw a0, 0(sp)
+        ebreak
+        .data
+        value: .word 1234
+    ");
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_registers()[10], 1234);
+    assert_eq!(cpu.view_word(0x8800_0000 - 4), Some(1234));
+}
+
+#[test]
+fn outside_ram_faults() {
+    // ram starts at 0x80000000 so address 0 isnt anything
+    let cpu = run(CpuConfig::preset("virt").unwrap(), "
+        lw a0, 0(x0)
+        ebreak
+    ");
+    assert!(cpu.view_fault().is_some());
+
+    // one past the end of ram
+    let config = CpuConfig::default().ram_size(0x1000);
+    let cpu = run(config, "
+        li t0, 0x1000
+        sw t0, 0(t0)
+        ebreak
+    ");
+    assert!(cpu.view_fault().is_some());
+}
+
+#[test]
+fn ram_right_at_the_top() {
+    // the pc and the link address wrap round to 0 instead of overflowing
+    let config = CpuConfig::default().ram_base(0xFFFF_F000).ram_size(0x1000).reset_vector(0xFFFF_FFF8).stack_pointer(0);
+    assert!(config.check().is_ok());
+    let cpu = run(config, "
+        nop
+        jal ra, 4
+    ");
+    assert_eq!(cpu.view_registers()[1], 0);
+    // theres nothing at 0 to run
+    assert_eq!(cpu.view_csrs().mepc, 0);
+    assert!(cpu.view_fault().is_some());
+
+    let cpu = run(config.reset_vector(0xFFFF_FFFC), "nop");
+    assert_eq!(cpu.view_csrs().mepc, 0);
+    assert!(cpu.view_fault().is_some());
+}
+
+#[test]
+fn programs_that_dont_fit() {
+    // 0x100 bytes of ram left above the reset vector on the default layout
+    let mut cpu = CPU::default();
+    assert!(cpu.load_program(&[0x13; 0x100]).is_ok());
+    assert_eq!(cpu.load_program(&[0; 0x104]), Err("program at 0x100 (260 bytes) does not fit in memory".to_string()));
+    // none of it got written
+    assert_eq!(cpu.view_word(0x100), Some(0x1313_1313));
+}
+
+#[test]
+fn space_past_the_image() {
+    let mut assembler = Assembler::from_source("ebreak\n.data\n.word 1\nbuffer: .space 0x100");
+    assembler.set_base(0x100);
+    let image = assembler.assemble_bytes().unwrap();
+    assert_eq!(image.len(), 8);
+
+    // it still has to fit, and whatever was there gets cleared
+    let mut cpu = CPU::default();
+    assert!(cpu.load_program_sized(&image, assembler.view_size()).is_err());
+    let mut cpu = CPU::new(CpuConfig::preset("small").unwrap()).unwrap();
+    cpu.load_program(&[0xFF; 0x200]).unwrap();
+    cpu.load_program_sized(&image, assembler.view_size()).unwrap();
+    assert_eq!(cpu.view_word(0x104), Some(1));
+    assert_eq!(cpu.view_word(0x108), Some(0));
+    assert_eq!(cpu.view_word(0x204), Some(0));
+    assert_eq!(cpu.view_word(0x208), Some(0xFFFF_FFFF));
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use common::{errors, load, run, words};
+use riscvemulator::Assembler;
+
+fn label(assembler: &Assembler, name: &str) -> u32 {
+    assembler.view_labels().into_iter().find(|(label, _)| label == name).unwrap().1
+}
+
+#[test]
+fn big_space() {
+    // bigger than a u16, for a buffer in a big layout
+    let source = "
+        ebreak
+        .data
+        buffer: .space 100000
+        after: .word 1
+    ";
+    // too big for the default ram so it only gets assembled
+    let assembler = Assembler::from_source(source);
+    assert_eq!(label(&assembler, "after"), 0x104 + 100_000);
+    assert_eq!(assembler.assemble_bytes().unwrap().len(), 4 + 100_000 + 4);
+
+    // space at the end isnt in the image, only in the size
+    let assembler = Assembler::from_source("ebreak\n.data\nbuffer: .space 0xC0000000");
+    assert_eq!(assembler.assemble_bytes().unwrap().len(), 4);
+    assert_eq!(assembler.view_size(), 0xC000_0004);
+    // and a big one in .text doesnt need a line number for every word
+    let assembler = Assembler::from_source("nop\n.space 0x10000000\nnop");
+    assert_eq!(assembler.address_of_line(3), Some(0x1000_0104));
+    assert_eq!(assembler.line_of_address(0x1000_0100), Some(2));
+
+    // only so much fits in the address space, counting where it gets loaded
+    assert_eq!(errors(".data\n.space 0x100000000"), ["0x100000000 is not a valid size"]);
+    assert_eq!(errors(".data\n.space 0x80000000\n.space 0x80000000").len(), 1);
+    assert_eq!(errors(".data\n.space 0xFFFFFF00"), ["0xFFFFFF00 is not a valid size"]);
+    assert_eq!(errors(".data\n.space 0xFFFFFEFC"), [] as [&str; 0]);
+    assert_eq!(errors(".space -1"), ["-1 is not a valid size"]);
+    let mut assembler = Assembler::from_source("nop\n.data\n.space 0x1000");
+    assembler.set_base(0xFFFF_F800);
+    let errors = assembler.assemble_bytes().unwrap_err();
+    assert_eq!(errors[0].message, "the program goes past the end of the address space when loaded at 0xfffff800");
+}
+
+#[test]
+fn data_goes_after_the_code() {
+    let source = "
+        .data
+        first: .byte 1, 2, 3
+        .text
+        la a0, first
+        lbu a1, 2(a0)
+        .data
+        second: .half 0x1234
+        .align 2
+        third: .word -1, third
+        .text
+        lw a2, 0(a0)
+        ebreak
+    ";
+    let (assembler, mut cpu) = load(source);
+    // 5 words of code, then the data in the order it was written
+    assert_eq!(label(&assembler, "first"), 0x114);
+    assert_eq!(label(&assembler, "second"), 0x117);
+    assert_eq!(label(&assembler, "third"), 0x11C);
+    assert_eq!(assembler.assemble_bytes().unwrap()[0x14..], [
+        1, 2, 3, 0x34, 0x12, 0, 0, 0,
+        0xFF, 0xFF, 0xFF, 0xFF, 0x1C, 0x01, 0, 0,
+    ]);
+    cpu.run(100);
+    assert_eq!(cpu.view_registers()[11], 3);
+    assert_eq!(cpu.view_registers()[12], 0x3403_0201);
+}
+
+#[test]
+fn alignment() {
+    // a big .align in .data lines the whole section up too
+    let source = "
+        nop
+        .data
+        .byte 1
+        .align 4
+        aligned: .byte 2
+        .balign 8
+        balign: .byte 3
+    ";
+    let (assembler, _) = load(source);
+    assert_eq!(label(&assembler, "aligned"), 0x110 + 0x10);
+    assert_eq!(label(&assembler, "balign"), 0x110 + 0x18);
+
+    // in .text its padding, and code has to be back on a word after bytes
+    let (assembler, _) = load("nop\n.byte 1\n.align 3\nhere: nop");
+    assert_eq!(label(&assembler, "here"), 0x108);
+    assert_eq!(errors("nop\n.byte 1\nnop"), ["instructions have to start on a word boundary, use .align 2 before this"]);
+
+    // the padding in .text is nops so code can run straight through it
+    assert_eq!(words("nop\n.align 3\nnop"), [0x13, 0x13, 0x13]);
+    assert_eq!(words("nop\n.byte 1\n.align 4\nnop"), [0x13, 1, 0x13, 0x13, 0x13]);
+    let cpu = run("li a0, 1\n.align 4\naddi a0, a0, 1\nebreak");
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_registers()[10], 2);
+    assert_eq!(errors(".align 13"), ["13 is not a valid alignment"]);
+    assert_eq!(errors(".balign 3"), ["3 is not a valid alignment"]);
+}
+
+#[test]
+fn strings_and_constants() {
+    let source = "
+        .equ COUNT, 3
+        .set SIZE, COUNT * 4
+        li a0, SIZE
+        ebreak
+        .data
+        a: .ascii \"ab\"
+        b: .asciz \"c\\n\"
+        c: .string \"\"
+        d: .space COUNT
+        e: .byte COUNT
+    ";
+    let (assembler, mut cpu) = load(source);
+    // the image gets padded out to a whole word
+    assert_eq!(assembler.assemble_bytes().unwrap()[8..], *b"abc\n\0\0\0\0\0\x03\0\0");
+    assert_eq!(label(&assembler, "b"), 0x10A);
+    assert_eq!(label(&assembler, "d"), 0x10E);
+    assert_eq!(label(&assembler, "e"), 0x111);
+    cpu.run(100);
+    assert_eq!(cpu.view_registers()[10], 12);
+}
+
+#[test]
+fn directive_errors() {
+    assert_eq!(errors(".data\nnop"), ["nop is an instruction, those have to go in .text"]);
+    assert_eq!(errors(".bss"), [".bss is not a known directive"]);
+    assert_eq!(errors(".word"), [".word needs a list of values"]);
+    assert_eq!(errors(".equ X, 1\n.equ X, 2"), ["X is defined more than once"]);
+    assert_eq!(errors("X: nop\n.equ X, 2"), ["X is defined more than once"]);
+    assert_eq!(errors(".equ 5"), [".equ needs a name and a value"]);
+}
@@ -0,0 +1,53 @@
+// whatever the disassembler prints, the assembler has to turn back into the same word
+use std::collections::HashMap;
+use proptest::prelude::*;
+use proptest::sample::select;
+use riscvemulator::{Assembler, Disassembler};
+
+const OPCODES: [u32; 10] = [0x33, 0x13, 0x03, 0x67, 0x23, 0x63, 0x37, 0x17, 0x6F, 0x73];
+
+fn reassemble(text: &str) -> Result<Vec<u32>, String> {
+    Assembler::from_source(text).assemble()
+        .map_err(|errors| format!("{} gave {}", text, errors[0]))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(20_000))]
+
+    // random words with a real opcode so most of them are real instructions,
+    // anything else comes out as a .word which goes back the same too
+    #[test]
+    fn disassemble_then_assemble(word in any::<u32>(), opcode in select(OPCODES.to_vec()), abi_names in any::<bool>()) {
+        let word = (word & !0x7F) | opcode;
+        let text = Disassembler::new(abi_names, HashMap::new()).disassemble(word, 0x100);
+        prop_assert_eq!(reassemble(&text), Ok(vec![word]), "{}", text);
+    }
+}
+
+#[test]
+fn labels_go_back_to_the_same_offsets() {
+    let source = "
+        start:
+        addi a0, a0, 1
+        bne a0, a1, start
+        beq x0, x0, end
+        jal ra, start
+        end: ebreak
+    ";
+    let assembler = Assembler::from_source(source);
+    let words = assembler.assemble().unwrap();
+    let labels = assembler.view_labels().into_iter().map(|(name, address)| (address, name)).collect::<HashMap<_, _>>();
+    let disassembler = Disassembler::new(true, labels.clone());
+
+    let mut text = String::new();
+    for (i, word) in words.iter().enumerate() {
+        let pc = 0x100 + 4 * i as u32;
+        if let Some(label) = labels.get(&pc) {
+            text += &format!("{}:\n", label);
+        }
+        text += &format!("{}\n", disassembler.disassemble(*word, pc));
+    }
+    assert!(text.contains("bne a0, a1, start"));
+    assert!(text.contains("beq zero, zero, end"));
+    assert_eq!(reassemble(&text), Ok(words));
+}
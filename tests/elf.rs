@@ -0,0 +1,143 @@
+// theres no riscv linker around to make real executables, so these build small ones by hand
+use riscvemulator::{Assembler, Elf, CPU};
+
+// (address, data, size in memory)
+type Segment<'a> = (u32, &'a [u8], u32);
+
+// header, program headers, segment data, then the symbol and string tables with their section headers
+fn build_elf(entry: u32, segments: &[Segment], symbols: &[(&str, u32, u8)]) -> Vec<u8> {
+    let mut strings = vec![0u8];
+    let mut symtab = vec![0u8; 16];
+    for (name, value, info) in symbols {
+        symtab.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+        symtab.extend_from_slice(&value.to_le_bytes());
+        symtab.extend_from_slice(&0u32.to_le_bytes());
+        symtab.extend_from_slice(&[*info, 0]);
+        symtab.extend_from_slice(&1u16.to_le_bytes());
+        strings.extend_from_slice(name.as_bytes());
+        strings.push(0);
+    }
+
+    let phoff = 52;
+    let mut data_offset = phoff + 32 * segments.len() as u32;
+    let mut headers = vec![];
+    let mut data = vec![];
+    for (address, bytes, size) in segments {
+        for field in [1, data_offset, *address, *address, bytes.len() as u32, *size, 7, 4] {
+            headers.extend_from_slice(&u32::to_le_bytes(field));
+        }
+        data.extend_from_slice(bytes);
+        data_offset += bytes.len() as u32;
+    }
+    let symtab_offset = data_offset;
+    let strings_offset = symtab_offset + symtab.len() as u32;
+    let shoff = strings_offset + strings.len() as u32;
+
+    let mut elf = vec![0x7F, b'E', b'L', b'F', 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    elf.extend_from_slice(&2u16.to_le_bytes()); // executable
+    elf.extend_from_slice(&0xF3u16.to_le_bytes()); // risc-v
+    elf.extend_from_slice(&1u32.to_le_bytes());
+    elf.extend_from_slice(&entry.to_le_bytes());
+    elf.extend_from_slice(&phoff.to_le_bytes());
+    elf.extend_from_slice(&shoff.to_le_bytes());
+    elf.extend_from_slice(&0u32.to_le_bytes());
+    for field in [52u16, 32, segments.len() as u16, 40, 3, 0] {
+        elf.extend_from_slice(&field.to_le_bytes());
+    }
+    elf.extend(headers);
+    elf.extend(data);
+    elf.extend(&symtab);
+    elf.extend(&strings);
+
+    // null, .symtab linking to .strtab, .strtab
+    elf.extend([0u8; 40]);
+    for field in [0, 2, 0, 0, symtab_offset, symtab.len() as u32, 2, 1, 4, 16] {
+        elf.extend_from_slice(&u32::to_le_bytes(field));
+    }
+    for field in [0, 3, 0, 0, strings_offset, strings.len() as u32, 0, 0, 1, 0] {
+        elf.extend_from_slice(&u32::to_le_bytes(field));
+    }
+    elf
+}
+
+fn assemble(source: &str) -> Vec<u8> {
+    Assembler::from_source(source).assemble_bytes().unwrap()
+}
+
+const FUNC: u8 = 0x12;
+const OBJECT: u8 = 0x11;
+const SECTION: u8 = 0x03;
+
+#[test]
+fn runs_from_the_entry_point() {
+    // the first word is skipped over by the entry point, bss starts out as junk
+    let code = assemble("
+        ebreak
+        lw t0, 0x180(x0)
+        lw t1, 0x184(x0)
+        lw t2, 0x188(x0)
+        add a0, t0, t1
+        ebreak
+    ");
+    let elf = build_elf(0x104, &[(0x100, &code, code.len() as u32), (0x180, &[7, 0, 0, 0, 5, 0, 0, 0], 16)], &[]);
+
+    let mut cpu = CPU::default();
+    cpu.reset();
+    cpu.load_program(&[0xAA; 0x100]).unwrap();
+    cpu.load_elf(&Elf::parse(&elf).unwrap()).unwrap();
+    assert_eq!(cpu.get_pc(), 0x104);
+
+    cpu.run(100);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_registers()[10], 12);
+    assert_eq!(cpu.view_registers()[7], 0);
+    assert_eq!((cpu.view_word(0x188), cpu.view_word(0x18C)), (Some(0), Some(0)));
+    // the byte after the bss is left alone
+    assert_eq!(cpu.view_byte(0x190), Some(0xAA));
+}
+
+#[test]
+fn symbols_sorted_by_address() {
+    let elf = build_elf(0x100, &[], &[
+        ("main", 0x120, FUNC),
+        ("_start", 0x100, FUNC),
+        ("counter", 0x180, OBJECT),
+        (".L3", 0x110, 0x10),
+        (".text", 0x100, SECTION),
+    ]);
+    let elf = Elf::parse(&elf).unwrap();
+    assert_eq!(elf.symbols, vec![
+        ("_start".to_string(), 0x100),
+        ("main".to_string(), 0x120),
+        ("counter".to_string(), 0x180),
+    ]);
+}
+
+#[test]
+fn rejects_what_it_cant_run() {
+    let good = build_elf(0x100, &[(0x100, &[0x73, 0, 0x10, 0], 4)], &[]);
+    assert!(Elf::parse(&good).is_ok());
+
+    let mut not_elf = good.clone();
+    not_elf[0] = 0;
+    let mut wide = good.clone();
+    wide[4] = 2;
+    let mut relocatable = good.clone();
+    relocatable[0x10] = 1;
+    let mut other_machine = good.clone();
+    other_machine[0x12] = 0x3E;
+
+    for elf in [not_elf, wide, relocatable, other_machine, good[..60].to_vec(), vec![]] {
+        assert!(Elf::parse(&elf).is_err());
+    }
+}
+
+#[test]
+fn segments_have_to_fit_in_memory() {
+    let elf = build_elf(0x100, &[(0x100, &[1, 2, 3, 4], 4), (0x1F0, &[], 0x100)], &[]);
+    let mut cpu = CPU::default();
+    cpu.reset();
+    assert!(cpu.load_elf(&Elf::parse(&elf).unwrap()).is_err());
+    // nothing gets loaded if any of it doesnt fit
+    assert_eq!(cpu.view_word(0x100), Some(0));
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::{errors, run, words};
+use riscvemulator::{Assembler, CpuConfig, CPU};
+
+fn value(text: &str) -> u32 {
+    words(&format!(".word {}", text))[0]
+}
+
+#[test]
+fn operators() {
+    assert_eq!(value("1 + 2 * 3"), 7);
+    assert_eq!(value("(1 + 2) * 3"), 9);
+    assert_eq!(value("1 << 4 + 1"), 32);
+    assert_eq!(value("0xF0 | 0x0F & 0x3C"), 0xFC);
+    assert_eq!(value("0xFF ^ 0x0F"), 0xF0);
+    assert_eq!(value("-7 / 2"), -3i32 as u32);
+    assert_eq!(value("7 % 4"), 3);
+    assert_eq!(value("~0"), u32::MAX);
+    assert_eq!(value("-(2 - 5)"), 3);
+    assert_eq!(value("0x100 >> 4"), 0x10);
+
+    // shifts are done in 64 bits and cant go past that
+    assert_eq!(value("1 << 40 >> 39"), 2);
+    assert_eq!(errors(".word 1 << 64"), ["1 << 64 shifts by 64, it has to be 0 to 63"]);
+    assert_eq!(errors("li a0, (1 >> 70) + 2"), ["(1 >> 70) + 2 shifts by 70, it has to be 0 to 63"]);
+    assert_eq!(errors(".word 1 << -1"), ["1 << -1 shifts by -1, it has to be 0 to 63"]);
+}
+
+#[test]
+fn symbols() {
+    let cpu = run("
+        .equ WIDTH, 4
+        start:
+        li a0, (end - start) / WIDTH
+        li a1, end - 8
+        addi a2, zero, WIDTH * WIDTH
+        end: ebreak
+    ");
+    let registers = cpu.view_registers();
+    // li with a label gets two words so end is 5 words in
+    assert_eq!(registers[10], 5);
+    assert_eq!(registers[11], 0x114 - 8);
+    assert_eq!(registers[12], 16);
+
+    // .equ only gets what came before it, labels might still move
+    assert_eq!(errors(".equ SIZE, end\nend: nop"), ["end is not a number or a known label"]);
+    assert_eq!(errors("li a0, nowhere"), ["nowhere is not a number or a known label"]);
+    assert_eq!(errors(".word 1 / 0"), ["division by zero"]);
+    assert_eq!(errors(".word (1 + 2"), ["missing a closing )"]);
+    assert_eq!(errors(".word 1 +"), ["expected a value"]);
+}
+
+#[test]
+fn hi_and_lo() {
+    // bit 11 of the low part is set so %hi has to be one more to make up for addi sign extending
+    let cpu = run("
+        lui a0, %hi(0x12345FFF)
+        addi a0, a0, %lo(0x12345FFF)
+        lui a1, %hi(0x12345678)
+        addi a1, a1, %lo(0x12345678)
+        lui a2, %hi(-1)
+        addi a2, a2, %lo(-1)
+        lui a3, %hi(data)
+        lw a3, %lo(data)(a3)
+        ebreak
+        .data
+        data: .word 0xCAFE
+    ");
+    let registers = cpu.view_registers();
+    assert_eq!(registers[10], 0x1234_5FFF);
+    assert_eq!(registers[11], 0x1234_5678);
+    assert_eq!(registers[12], u32::MAX);
+    assert_eq!(registers[13], 0xCAFE);
+    assert_eq!(value("%hi(0x12345FFF)"), 0x12346);
+    assert_eq!(value("%lo(0x12345FFF)"), u32::MAX);
+    assert_eq!(value("%hi(0x7FFFF800)"), 0x80000);
+}
+
+#[test]
+fn pcrel() {
+    // far enough away that the low part has bit 11 set, which needs more than the default memory
+    let assembler = Assembler::from_source("
+        here: auipc a0, %pcrel_hi(data)
+        addi a0, a0, %pcrel_lo(here)
+        there: auipc a1, %pcrel_hi(data + 4)
+        lw a1, %pcrel_lo(there)(a1)
+        ebreak
+        .space 0xC00
+        data: .word 0xBEEF, 0xCAFE
+    ");
+    let data = assembler.view_labels().into_iter().find(|(label, _)| label == "data").unwrap().1;
+    assert_eq!(data, 0x114 + 0xC00);
+    let mut cpu = CPU::new(CpuConfig::preset("small").unwrap()).unwrap();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+    cpu.run(100);
+    assert_eq!(cpu.view_registers()[10], data);
+    assert_eq!(cpu.view_registers()[11], 0xCAFE);
+
+    assert_eq!(errors("addi a0, a0, %pcrel_lo(1)"), ["%pcrel_lo needs the label of its auipc"]);
+    assert_eq!(errors("addi a0, a0, %lo+4"), ["%lo needs its argument in brackets"]);
+    assert_eq!(errors("addi a0, a0, %mid(4)"), ["%mid is not a known operator"]);
+}
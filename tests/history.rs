@@ -0,0 +1,197 @@
+mod common;
+
+use common::load;
+use riscvemulator::{Assembler, CpuConfig, SyscallAbi, Trap, CPU};
+
+const LOOP: &str = "
+    li t0, 3
+    li t1, 0x180
+loop:
+    sw t0, 0(t1)
+    addi t1, t1, 4
+    addi t0, t0, -1
+    bnez t0, loop
+    csrr a1, mepc
+    lw a0, 4(zero)
+    ebreak
+";
+
+// everything a step could change
+fn state(cpu: &CPU) -> (u32, [u32; 32], Vec<Option<u8>>, u64, u64) {
+    let memory = (0x180..0x190).map(|address| cpu.view_byte(address)).collect();
+    let csrs = cpu.view_csrs();
+    (cpu.get_pc(), *cpu.view_registers(), memory, csrs.cycle, csrs.instret)
+}
+
+#[test]
+fn stepping_back_undoes_each_step() {
+    let (_, mut cpu) = load(LOOP);
+    let mut states = vec![state(&cpu)];
+    for _ in 0..10 {
+        cpu.step();
+        states.push(state(&cpu));
+    }
+    assert_eq!(cpu.view_history_len(), 10);
+    assert_eq!(cpu.view_word(0x184), Some(2));
+
+    states.pop();
+    while let Some(before) = states.pop() {
+        assert!(cpu.step_back());
+        assert_eq!(state(&cpu), before);
+    }
+    assert!(!cpu.step_back());
+    assert_eq!(cpu.view_word(0x180), Some(0));
+
+    // and it all runs the same again
+    cpu.run(u64::MAX);
+    assert_eq!(cpu.view_word(0x188), Some(1));
+}
+
+#[test]
+fn faults_and_exits_can_be_undone() {
+    // a word from 2 isnt lined up
+    let (_, mut cpu) = load(&LOOP.replace("4(zero)", "2(zero)"));
+    cpu.run(u64::MAX);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_fault(), Some(&Trap::LoadAddressMisaligned(2)));
+
+    assert!(cpu.step_back());
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_csrs().mcause, 0);
+    assert_eq!(cpu.get_pc(), 0x11C);
+
+    let config = CpuConfig::preset("small").unwrap().syscalls(SyscallAbi::Rars);
+    let mut assembler = Assembler::from_source("
+        li a0, 16
+        li a7, 9
+        ecall
+        mv s0, a0
+        li a0, 16
+        ecall
+        li a7, 10
+        ecall
+    ");
+    assembler.set_base(config.reset_vector);
+    let mut cpu = CPU::new(config).unwrap();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+    cpu.run(100);
+    assert_eq!(cpu.view_exit_code(), Some(0));
+    let second = cpu.view_registers()[10];
+
+    // back past the exit and the second sbrk, which hands out the same memory again
+    cpu.step_back();
+    assert_eq!(cpu.view_exit_code(), None);
+    cpu.step_back();
+    cpu.step_back();
+    assert_eq!(cpu.get_pc(), 0x114);
+    cpu.run(100);
+    assert_eq!(cpu.view_registers()[10], second);
+    assert_eq!(cpu.view_exit_code(), Some(0));
+}
+
+#[test]
+fn reverse_continue_stops_at_breakpoints() {
+    let (assembler, mut cpu) = load(LOOP);
+    cpu.run(u64::MAX);
+    let body = assembler.address_of_line(5).unwrap();
+    cpu.set_breakpoint(body);
+
+    // the last time round the loop
+    assert_eq!(cpu.reverse_continue(u64::MAX), 7);
+    assert_eq!(cpu.get_pc(), body);
+    assert_eq!(cpu.view_registers()[5], 1);
+    assert_eq!(cpu.view_word(0x188), Some(0));
+
+    // starting on one goes past it
+    assert_eq!(cpu.reverse_continue(u64::MAX), 4);
+    assert_eq!(cpu.view_registers()[5], 2);
+
+    // and runs out at the start
+    cpu.remove_breakpoint(body);
+    cpu.reverse_continue(u64::MAX);
+    assert_eq!(cpu.get_pc(), 0x100);
+    assert_eq!(cpu.view_history_len(), 0);
+}
+
+#[test]
+fn history_is_bounded() {
+    let (_, mut cpu) = load(LOOP);
+    cpu.set_history_limit(4);
+    cpu.run(u64::MAX);
+    assert_eq!(cpu.view_history_len(), 4);
+
+    // shrinking it drops the oldest
+    cpu.set_history_limit(2);
+    assert_eq!(cpu.view_history_len(), 2);
+    assert_eq!(cpu.reverse_continue(u64::MAX), 2);
+    assert_eq!(cpu.get_pc(), 0x11C);
+    assert!(!cpu.step_back());
+
+    // none kept at all
+    cpu.set_history_limit(0);
+    cpu.step();
+    assert!(!cpu.step_back());
+
+    // a reset cant be undone
+    cpu.set_history_limit(10);
+    cpu.step();
+    cpu.reset();
+    assert_eq!(cpu.view_history_len(), 0);
+}
+
+#[test]
+fn waiting_on_input_isnt_a_step() {
+    let config = CpuConfig::preset("small").unwrap().syscalls(SyscallAbi::Rars);
+    let mut assembler = Assembler::from_source("
+        li a0, 7
+        li a7, 5
+        ecall
+        ebreak
+    ");
+    assembler.set_base(config.reset_vector);
+    let mut cpu = CPU::new(config).unwrap();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+
+    // nothing typed yet so it sits on the ecall without counting or keeping anything
+    cpu.run(20_000);
+    assert_eq!(cpu.get_pc(), 0x108);
+    assert_eq!(cpu.view_history_len(), 2);
+    assert_eq!(cpu.view_csrs().cycle, 2);
+    assert_eq!(cpu.view_csrs().instret, 2);
+
+    cpu.view_uart().unwrap().receive(b"42\n");
+    cpu.run(100);
+    assert_eq!(cpu.view_registers()[10], 42);
+    // the ebreak traps so it doesnt count
+    assert_eq!(cpu.view_csrs().instret, 3);
+
+    // straight back past the wait
+    cpu.step_back();
+    cpu.step_back();
+    assert_eq!(cpu.get_pc(), 0x108);
+    assert_eq!(cpu.view_registers()[10], 7);
+    cpu.step_back();
+    assert_eq!(cpu.get_pc(), 0x104);
+}
+
+#[test]
+fn device_registers_arent_put_back() {
+    let (_, mut cpu) = load("
+        li t0, 0x10000000
+        li t1, 1
+        sb t1, 2(t0)    # fifo on
+        sb t1, 7(t0)    # scratch
+        ebreak
+    ");
+    cpu.run(100);
+    assert_eq!(cpu.view_byte(0x1000_0002), Some(0xC1));
+    cpu.view_uart().unwrap().receive(b"ab");
+
+    // reading iir back into fcr would be a different write, so the uart keeps what it has
+    cpu.reverse_continue(3);
+    assert_eq!(cpu.get_pc(), 0x108);
+    assert_eq!(cpu.view_byte(0x1000_0007), Some(1));
+    assert_eq!(cpu.view_byte(0x1000_0002), Some(0xC1));
+    assert_eq!(cpu.view_byte(0x1000_0005).map(|lsr| lsr & 1), Some(1));
+}
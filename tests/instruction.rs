@@ -0,0 +1,105 @@
+// the cpu decodes and the assembler encodes with the same Instruction, these make sure
+// going either way and back again never changes anything
+use proptest::prelude::*;
+use proptest::sample::select;
+use riscvemulator::{Fields, Format, Instruction};
+
+const OPCODES: [u32; 10] = [0x33, 0x13, 0x03, 0x67, 0x23, 0x63, 0x37, 0x17, 0x6F, 0x73];
+
+// cuts random fields down to what the format can actually hold
+fn valid(format: Format, fields: Fields) -> Fields {
+    let Fields { rd, rs1, rs2, imm } = fields;
+    let imm = match format {
+        Format::R | Format::System => 0,
+        Format::I | Format::Load | Format::Store => (imm << 20) >> 20,
+        Format::Shift => imm & 0x1F,
+        Format::Branch => ((imm << 19) >> 19) & !1,
+        Format::Upper => imm & !0xFFF,
+        Format::Jump => ((imm << 11) >> 11) & !1,
+        Format::Csr | Format::CsrImm => imm & 0xFFF,
+    };
+    Fields { rd, rs1, rs2, imm }
+}
+
+fn instruction() -> impl Strategy<Value = Instruction> {
+    let names = Instruction::names().collect::<Vec<_>>();
+    (select(names), 0..32u8, 0..32u8, 0..32u8, any::<i32>()).prop_map(|(name, rd, rs1, rs2, imm)| {
+        let format = Instruction::format_of(name).unwrap();
+        Instruction::new(name, valid(format, Fields { rd, rs1, rs2, imm })).unwrap()
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(20_000))]
+
+    #[test]
+    fn encode_then_decode(instruction in instruction()) {
+        prop_assert_eq!(Instruction::decode(instruction.encode()), Some(instruction));
+    }
+
+    // random words with a real opcode so most of them are worth decoding
+    #[test]
+    fn decode_then_encode(word in any::<u32>(), opcode in select(OPCODES.to_vec())) {
+        let word = (word & !0x7F) | opcode;
+        if let Some(instruction) = Instruction::decode(word) {
+            prop_assert_eq!(instruction.encode(), word);
+        }
+    }
+
+    #[test]
+    fn decode_any_word(word in any::<u32>()) {
+        if let Some(instruction) = Instruction::decode(word) {
+            prop_assert_eq!(instruction.encode(), word);
+        }
+    }
+
+    #[test]
+    fn fields_round_trip(instruction in instruction()) {
+        prop_assert_eq!(Instruction::new(instruction.name(), instruction.fields()), Some(instruction));
+    }
+}
+
+// every name in the table has to build, otherwise the random ones above would never hit it
+#[test]
+fn every_instruction_is_covered() {
+    let names = Instruction::names().collect::<Vec<_>>();
+    assert_eq!(names.len(), 54);
+    for name in names {
+        let instruction = Instruction::new(name, Fields::default()).unwrap();
+        assert_eq!(instruction.name(), name);
+        assert_eq!(Instruction::decode(instruction.encode()), Some(instruction));
+    }
+}
+
+// a few known encodings so both directions cant be wrong in the same way
+#[test]
+fn known_words() {
+    let cases = [
+        (0x00500093, Instruction::Addi { rd: 1, rs1: 0, imm: 5 }),
+        (0xfff00093, Instruction::Addi { rd: 1, rs1: 0, imm: -1 }),
+        (0x002081b3, Instruction::Add { rd: 3, rs1: 1, rs2: 2 }),
+        (0x40f757b3, Instruction::Sra { rd: 15, rs1: 14, rs2: 15 }),
+        (0x41f0d093, Instruction::Srai { rd: 1, rs1: 1, shamt: 31 }),
+        (0x0000a103, Instruction::Lw { rd: 2, rs1: 1, imm: 0 }),
+        (0xfe112e23, Instruction::Sw { rs1: 2, rs2: 1, imm: -4 }),
+        (0xfe208ee3, Instruction::Beq { rs1: 1, rs2: 2, imm: -4 }),
+        (0x123452b7, Instruction::Lui { rd: 5, imm: 0x12345000 }),
+        (0x0080006f, Instruction::Jal { rd: 0, imm: 8 }),
+        (0x30529073, Instruction::Csrrw { rd: 0, rs1: 5, csr: 0x305 }),
+        (0x00000073, Instruction::Ecall),
+        (0x00100073, Instruction::Ebreak),
+        (0x30200073, Instruction::Mret),
+    ];
+    for (word, instruction) in cases {
+        assert_eq!(Instruction::decode(word), Some(instruction), "{:08x}", word);
+        assert_eq!(instruction.encode(), word, "{:?}", instruction);
+    }
+}
+
+#[test]
+fn rejects_bad_words() {
+    // add with funct7 0x02, a load with funct3 7, ecall with rd set and an opcode that doesnt exist
+    for word in [0x042081b3, 0x0000f103, 0x000000f3, 0x0000007f] {
+        assert_eq!(Instruction::decode(word), None, "{:08x}", word);
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn jal_links_the_next_instruction() {
+    let cpu = run("
+        jal ra, function    # 0x100
+        li a1, 2
+        ebreak
+        function:
+        mv a0, ra
+        jal x0, 4           # doesnt link anywhere
+        ret
+    ");
+    let registers = cpu.view_registers();
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(registers[10], 0x104);
+    assert_eq!(registers[11], 2);
+    assert_eq!(registers[0], 0);
+}
+
+#[test]
+fn jalr_clears_the_low_bit() {
+    // 0x10D is odd, it ends up at 0x10C
+    let cpu = run("
+        li t0, 0x10D
+        jalr a0, 0(t0)      # 0x104
+        ebreak
+        li a1, 1
+        ebreak
+    ");
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_registers()[10], 0x108);
+    assert_eq!(cpu.view_registers()[11], 1);
+
+    // the offset is added before, and the link is written after reading rs1
+    let cpu = run("
+        li ra, 0x108
+        jalr ra, 5(ra)      # 0x104
+        ebreak
+        li a1, 1
+        ebreak
+    ");
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_registers()[1], 0x108);
+    assert_eq!(cpu.view_registers()[11], 1);
+}
+
+#[test]
+fn auipc_adds_to_its_own_address() {
+    let cpu = run("
+        nop
+        auipc a0, 0         # 0x104
+        auipc a1, 1
+        auipc a2, 0xFFFFF
+        lui a3, 0xFFFFF
+        ebreak
+    ");
+    let registers = cpu.view_registers();
+    assert_eq!(registers[10], 0x104);
+    assert_eq!(registers[11], 0x1108);
+    // wraps round to just below it
+    assert_eq!(registers[12], 0x10C_u32.wrapping_sub(0x1000));
+    assert_eq!(registers[13], 0xFFFF_F000);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{errors, words};
+
+// what a literal comes out as, through .word so nothing else touches it
+fn value(text: &str) -> u32 {
+    words(&format!(".word {}", text))[0]
+}
+
+#[test]
+fn bases() {
+    assert_eq!(value("42"), 42);
+    assert_eq!(value("0x2A"), 42);
+    assert_eq!(value("0X2a"), 42);
+    assert_eq!(value("0b101010"), 42);
+    assert_eq!(value("0o52"), 42);
+    // used to turn into 010
+    assert_eq!(value("0x10"), 16);
+    assert_eq!(value("-0x10"), -16i32 as u32);
+    assert_eq!(value("-0b1"), u32::MAX);
+}
+
+#[test]
+fn underscores() {
+    assert_eq!(value("0xFFFF_0000"), 0xFFFF_0000);
+    assert_eq!(value("1_000_000"), 1_000_000);
+    assert_eq!(value("0b1111_0000"), 0xF0);
+    // only between digits
+    assert_eq!(errors(".word 0x_FF").len(), 1);
+    assert_eq!(errors(".word 1_").len(), 1);
+}
+
+#[test]
+fn characters() {
+    assert_eq!(value("'A'"), 65);
+    assert_eq!(value("'\\n'"), 10);
+    assert_eq!(value("'\\0'"), 0);
+    assert_eq!(value("'\\''"), 39);
+    assert_eq!(value("'\\\\'"), 92);
+    assert_eq!(words("li a0, 'z'"), words("li a0, 122"));
+    assert_eq!(errors(".word 'ab'").len(), 1);
+    assert_eq!(errors(".word '\\q'").len(), 1);
+}
+
+#[test]
+fn bad_literals_and_ranges_name_the_literal() {
+    assert_eq!(errors("addi a0, a0, 0x800"), ["0x800 is not a valid imm value, it has to fit in 12 bits"]);
+    assert_eq!(errors("addi a0, a0, -0x801"), ["-0x801 is not a valid imm value, it has to fit in 12 bits"]);
+    assert_eq!(words("addi a0, a0, -0x800"), words("addi a0, a0, -2048"));
+    assert_eq!(errors("slli a0, a0, 0b100000"), ["0b100000 is not a valid shift amount, it has to be 0 to 31"]);
+    assert_eq!(errors("lui a0, 0x100000"), ["0x100000 is not a valid imm value, it has to fit in 20 bits"]);
+    assert_eq!(errors("csrrwi x0, mstatus, 0x20"), ["0x20 is not a valid uimm, it has to be 0 to 31"]);
+    assert_eq!(errors("addi a0, a0, 0b102"), ["0b102 is not a valid number"]);
+    assert_eq!(errors("addi a0, a0, 0o8"), ["0o8 is not a valid number"]);
+}
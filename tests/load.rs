@@ -0,0 +1,51 @@
+mod common;
+
+use common::run;
+
+// loads from a few bytes with the top bit set, gives back a0
+fn load(instruction: &str) -> u32 {
+    let cpu = run(&format!("
+        la t0, data
+        {}
+        ebreak
+        .data
+        data: .byte 0x80, 0xFF, 0x7F, 0x01
+        .half 0xFFFF, 0x8001
+    ", instruction));
+    assert_eq!(cpu.view_fault(), None);
+    cpu.view_registers()[10]
+}
+
+#[test]
+fn signed_loads_extend_the_sign() {
+    assert_eq!(load("lb a0, 0(t0)"), 0xFFFF_FF80);
+    assert_eq!(load("lb a0, 1(t0)"), 0xFFFF_FFFF);
+    assert_eq!(load("lb a0, 2(t0)"), 0x7F);
+    assert_eq!(load("lh a0, 4(t0)"), 0xFFFF_FFFF);
+    assert_eq!(load("lh a0, 6(t0)"), 0xFFFF_8001);
+    assert_eq!(load("lh a0, 2(t0)"), 0x017F);
+}
+
+#[test]
+fn unsigned_loads_fill_with_zeros() {
+    assert_eq!(load("lbu a0, 0(t0)"), 0x80);
+    assert_eq!(load("lbu a0, 1(t0)"), 0xFF);
+    assert_eq!(load("lhu a0, 4(t0)"), 0xFFFF);
+    assert_eq!(load("lhu a0, 6(t0)"), 0x8001);
+    assert_eq!(load("lw a0, 0(t0)"), 0x017F_FF80);
+}
+
+#[test]
+fn stores_only_write_their_width() {
+    let cpu = run("
+        li t0, 0x180
+        li t1, -1
+        sw t1, 0(t0)
+        li t1, 0x1234
+        sb t1, 0(t0)
+        sh t1, 2(t0)
+        lw a0, 0(t0)
+        ebreak
+    ");
+    assert_eq!(cpu.view_registers()[10], 0x1234_FF34);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::run;
+
+// runs op on a and b and gives back rd
+fn op(name: &str, a: i64, b: i64) -> u32 {
+    let cpu = run(&format!("
+        li a0, {}
+        li a1, {}
+        {} a2, a0, a1
+        ebreak
+    ", a as u32, b as u32, name));
+    assert_eq!(cpu.view_fault(), None);
+    cpu.view_registers()[12]
+}
+
+const MIN: i64 = i32::MIN as i64;
+
+#[test]
+fn multiplying() {
+    assert_eq!(op("mul", 6, 7), 42);
+    assert_eq!(op("mul", -6, 7), -42i32 as u32);
+    // only the low half
+    assert_eq!(op("mul", 0x10000, 0x10001), 0x10000);
+    assert_eq!(op("mul", MIN, -1), MIN as u32);
+
+    // the high half, with each side signed or not
+    assert_eq!(op("mulh", -1, -1), 0);
+    assert_eq!(op("mulhu", -1, -1), 0xFFFF_FFFE);
+    assert_eq!(op("mulhsu", -1, -1), 0xFFFF_FFFF);
+    assert_eq!(op("mulh", MIN, MIN), 0x4000_0000);
+    assert_eq!(op("mulhu", MIN, MIN), 0x4000_0000);
+    assert_eq!(op("mulhsu", MIN, MIN), 0xC000_0000);
+    assert_eq!(op("mulh", 0x10000, 0x10000), 1);
+    assert_eq!(op("mulhsu", 3, -1), 2);
+}
+
+#[test]
+fn dividing_rounds_towards_zero() {
+    assert_eq!(op("div", 7, 2), 3);
+    assert_eq!(op("div", -7, 2), -3i32 as u32);
+    assert_eq!(op("rem", -7, 2), -1i32 as u32);
+    assert_eq!(op("rem", 7, -2), 1);
+    assert_eq!(op("divu", -7, 2), 0x7FFF_FFFC);
+    assert_eq!(op("remu", -7, 2), 1);
+}
+
+// the spec says what these give instead of trapping
+#[test]
+fn dividing_by_zero() {
+    assert_eq!(op("div", 42, 0), u32::MAX);
+    assert_eq!(op("div", -42, 0), u32::MAX);
+    assert_eq!(op("divu", 42, 0), u32::MAX);
+    assert_eq!(op("rem", -42, 0), -42i32 as u32);
+    assert_eq!(op("remu", 42, 0), 42);
+}
+
+#[test]
+fn overflowing_division() {
+    assert_eq!(op("div", MIN, -1), MIN as u32);
+    assert_eq!(op("rem", MIN, -1), 0);
+    // unsigned its just a big number
+    assert_eq!(op("divu", MIN, -1), 0);
+    assert_eq!(op("remu", MIN, -1), MIN as u32);
+}
@@ -0,0 +1,120 @@
+// pseudo instructions get expanded before the labels are worked out, so the size
+// they were given room for has to match what they turn into
+mod common;
+
+use common::{errors, load, words};
+
+// where a label ended up
+fn label(source: &str, name: &str) -> u32 {
+    let (assembler, _) = load(source);
+    assembler.view_labels().into_iter().find(|(label, _)| label == name).unwrap().1
+}
+
+// runs li and checks both the value and that the label after it is right after it
+fn li(value: &str, expected: u32, size: u32) {
+    let source = format!("li a0, {}\nend: ebreak", value);
+    assert_eq!(words(&source).len() as u32, size + 1, "li a0, {}", value);
+    assert_eq!(label(&source, "end"), 0x100 + 4 * size, "li a0, {}", value);
+    let (_, mut cpu) = load(&source);
+    cpu.run(100);
+    assert_eq!(cpu.view_registers()[10], expected, "li a0, {}", value);
+}
+
+#[test]
+fn li_sizes() {
+    // fits in addi
+    li("0", 0, 1);
+    li("2047", 2047, 1);
+    li("-2048", -2048i32 as u32, 1);
+    li("0xFFFFFFFF", u32::MAX, 1);
+    // just lui
+    li("0x12345000", 0x1234_5000, 1);
+    li("0x80000000", 0x8000_0000, 1);
+    // both, including the ones where addi being negative pushes lui up one
+    li("0x12345678", 0x1234_5678, 2);
+    li("2048", 2048, 2);
+    li("0x12345FFF", 0x1234_5FFF, 2);
+    li("-2049", -2049i32 as u32, 2);
+    li("0x7FFFFFFF", 0x7FFF_FFFF, 2);
+
+    assert_eq!(errors("li a0, 0x100000000"), ["0x100000000 is not a valid 32 bit value"]);
+    assert_eq!(errors("li a0").len(), 1);
+}
+
+#[test]
+fn li_without_a_value_yet() {
+    // labels and .equs that come later arent known when its sized, so they always get two
+    let source = "
+        li a0, end
+        li a1, SMALL
+        li a2, BIG
+        end: ebreak
+        .equ SMALL, 5
+        .equ BIG, 0x12345678
+    ";
+    assert_eq!(words(source).len(), 7);
+    assert_eq!(label(source, "end"), 0x118);
+    let (_, mut cpu) = load(source);
+    cpu.run(100);
+    assert_eq!(cpu.view_registers()[10..13], [0x118, 5, 0x1234_5678]);
+
+    // one thats already been seen is known
+    let source = ".equ SMALL, 5\nli a0, SMALL\nend: ebreak";
+    assert_eq!(label(source, "end"), 0x104);
+}
+
+#[test]
+fn auipc_pairs() {
+    let source = "
+        la a0, data
+        call function
+        tail done
+        function:
+        li a1, 7
+        ret
+        done:
+        li a2, 9
+        ebreak
+        data: .word 0x1234
+    ";
+    assert_eq!(label(source, "function"), 0x118);
+    assert_eq!(label(source, "done"), 0x120);
+    assert_eq!(label(source, "data"), 0x128);
+    let (_, mut cpu) = load(source);
+    cpu.run(100);
+    let registers = cpu.view_registers();
+    assert_eq!(registers[10], 0x128);
+    assert_eq!(cpu.view_word(registers[10]), Some(0x1234));
+    // call linked back to the tail, which doesnt link
+    assert_eq!(registers[1], 0x110);
+    assert_eq!(registers[11], 7);
+    assert_eq!(registers[12], 9);
+}
+
+#[test]
+fn single_word_ones() {
+    // each is the real instruction it stands for
+    let pairs = [
+        ("nop", "addi x0, x0, 0"),
+        ("mv a0, a1", "addi a0, a1, 0"),
+        ("not a0, a1", "xori a0, a1, -1"),
+        ("neg a0, a1", "sub a0, x0, a1"),
+        ("seqz a0, a1", "sltiu a0, a1, 1"),
+        ("snez a0, a1", "sltu a0, x0, a1"),
+        ("bgt a0, a1, 8", "blt a1, a0, 8"),
+        ("bleu a0, a1, 8", "bgeu a1, a0, 8"),
+        ("blez a0, 8", "bge x0, a0, 8"),
+        ("j 8", "jal x0, 8"),
+        ("jr a0", "jalr x0, 0(a0)"),
+        ("jalr a0", "jalr x1, 0(a0)"),
+        ("ret", "jalr x0, 0(x1)"),
+        ("csrr a0, mepc", "csrrs a0, mepc, x0"),
+        ("csrwi mstatus, 8", "csrrwi x0, mstatus, 8"),
+        ("rdcycle a0", "csrrs a0, cycle, x0"),
+    ];
+    for (pseudo, real) in pairs {
+        assert_eq!(words(pseudo), words(real), "{}", pseudo);
+    }
+    assert_eq!(errors("ret x1"), ["ret expects 0 operand(s) but found 1"]);
+    assert_eq!(errors("mv a0"), ["mv expects 2 operand(s) but found 1"]);
+}
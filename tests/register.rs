@@ -0,0 +1,48 @@
+mod common;
+
+use std::collections::HashMap;
+use common::{errors, run, words};
+use riscvemulator::register::{abi_name, register_number};
+use riscvemulator::Disassembler;
+
+#[test]
+fn abi_names_are_the_same_registers() {
+    assert_eq!(words("add s0, sp, ra"), words("add x8, x2, x1"));
+    assert_eq!(words("addi fp, zero, 1"), words("addi x8, x0, 1"));
+    assert_eq!(words("sw a0, -4(fp)"), words("sw x10, -4(x8)"));
+    assert_eq!(words("lw t6, 0(s11)"), words("lw x31, 0(x27)"));
+    assert_eq!(words("sub a7, t3, s2"), words("sub x17, x28, x18"));
+    // a hex immediate has an x in it but isnt a register
+    assert_eq!(words("addi a0, x0, 0x10"), words("addi x10, x0, 16"));
+
+    let cpu = run("li a0, 5\nmv fp, a0\naddi s0, s0, 1\nebreak");
+    assert_eq!(cpu.view_registers()[8], 6);
+}
+
+#[test]
+fn bad_spellings_arent_registers() {
+    for name in ["x05", "x32", "x+5", "x", "X5", "A0", "a8", "s12", "t7", "f0", "zero1"] {
+        assert_eq!(register_number(name), None, "{}", name);
+    }
+    assert_eq!(errors("add x05, x1, x2"), ["x05 is not a valid register"]);
+    assert_eq!(errors("addi a0, x32, 1"), ["x32 is not a valid register"]);
+    assert_eq!(errors("mv a8, a0"), ["a8 is not a valid register"]);
+
+    for r in 0..32 {
+        assert_eq!(register_number(&format!("x{}", r)), Some(r));
+        assert_eq!(register_number(abi_name(r)), Some(r));
+    }
+    // fp only goes one way, s0 is what gets shown
+    assert_eq!(register_number("fp"), Some(8));
+    assert_eq!(abi_name(8), "s0");
+}
+
+#[test]
+fn disassembler_uses_abi_names_when_asked() {
+    let word = words("sw ra, 12(sp)")[0];
+    assert_eq!(Disassembler::new(true, HashMap::new()).disassemble(word, 0x100), "sw ra, 12(sp)");
+    assert_eq!(Disassembler::new(false, HashMap::new()).disassemble(word, 0x100), "sw x1, 12(x2)");
+    let word = words("add zero, fp, t6")[0];
+    assert_eq!(Disassembler::new(true, HashMap::new()).disassemble(word, 0x100), "add zero, s0, t6");
+    assert_eq!(Disassembler::new(false, HashMap::new()).disassemble(word, 0x100), "add x0, x8, x31");
+}
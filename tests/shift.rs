@@ -0,0 +1,48 @@
+mod common;
+
+use common::{errors, run, words};
+
+// runs op on a and b and gives back rd, b is the shamt for the immediate ones
+fn op(name: &str, a: u32, b: u32) -> u32 {
+    let operand = if name.ends_with('i') { b.to_string() } else { "a1".to_string() };
+    let cpu = run(&format!("
+        li a0, {}
+        li a1, {}
+        {} a2, a0, {}
+        ebreak
+    ", a, b, name, operand));
+    assert_eq!(cpu.view_fault(), None);
+    cpu.view_registers()[12]
+}
+
+#[test]
+fn register_shifts_use_the_low_5_bits() {
+    assert_eq!(op("sll", 1, 4), 0x10);
+    // 33 is 1 and 32 is 0
+    assert_eq!(op("sll", 1, 33), 2);
+    assert_eq!(op("sll", 1, 32), 1);
+    assert_eq!(op("srl", 0x8000_0000, 0xFFFF_FFFF), 1);
+    assert_eq!(op("sra", 0x8000_0000, 0xFFFF_FFFC), 0xFFFF_FFF8);
+    assert_eq!(op("srl", 0x100, 0x48), 1);
+}
+
+#[test]
+fn arithmetic_and_logical() {
+    // srai copies the sign bit in, srli brings in zeros
+    assert_eq!(op("srai", 0x8000_0000, 4), 0xF800_0000);
+    assert_eq!(op("srli", 0x8000_0000, 4), 0x0800_0000);
+    assert_eq!(op("srai", 0x7000_0000, 4), 0x0700_0000);
+    assert_eq!(op("srai", 0xFFFF_FFF0, 31), 0xFFFF_FFFF);
+    assert_eq!(op("srli", 0xFFFF_FFF0, 31), 1);
+    assert_eq!(op("slli", 0xFFFF_FFFF, 31), 0x8000_0000);
+    assert_eq!(op("sra", 0x8000_0000, 4), 0xF800_0000);
+    assert_eq!(op("srl", 0x8000_0000, 4), 0x0800_0000);
+}
+
+#[test]
+fn shift_amounts() {
+    // srai is srli with bit 30 set
+    assert_eq!(words("srli a0, a1, 3")[0] | 0x4000_0000, words("srai a0, a1, 3")[0]);
+    assert_eq!(errors("slli a0, a0, 32"), ["32 is not a valid shift amount, it has to be 0 to 31"]);
+    assert_eq!(errors("srai a0, a0, -1"), ["-1 is not a valid shift amount, it has to be 0 to 31"]);
+}
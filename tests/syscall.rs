@@ -0,0 +1,329 @@
+use std::fs;
+use riscvemulator::{Assembler, CpuConfig, SyscallAbi, Trap, CPU};
+
+fn cpu(abi: SyscallAbi, source: &str) -> CPU {
+    let config = CpuConfig::preset("small").unwrap().syscalls(abi);
+    let mut assembler = Assembler::from_source(source);
+    assembler.set_base(config.reset_vector);
+    let mut cpu = CPU::new(config).unwrap();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+    cpu
+}
+
+fn output(cpu: &CPU) -> String {
+    String::from_utf8(cpu.view_uart().unwrap().take_output()).unwrap()
+}
+
+#[test]
+fn rars_printing() {
+    let mut cpu = cpu(SyscallAbi::Rars, "
+        li a0, -42
+        li a7, 1
+        ecall
+        li a0, ' '
+        li a7, 11
+        ecall
+        la a0, message
+        li a7, 4
+        ecall
+        li a0, 3
+        li a7, 17
+        ecall
+        .data
+        message: .string \"hi there\"
+    ");
+    cpu.run(100);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_exit_code(), Some(3));
+    assert_eq!(output(&cpu), "-42 hi there");
+}
+
+#[test]
+fn read_int_waits_for_a_line() {
+    let mut cpu = cpu(SyscallAbi::Rars, "
+        li a7, 5
+        ecall
+        li a7, 10
+        ecall
+    ");
+    cpu.run(100);
+    // still sat on the ecall
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.get_pc(), 0x104);
+
+    let uart = cpu.view_uart().unwrap().clone();
+    uart.receive(b" 12");
+    cpu.run(100);
+    assert!(!cpu.is_halted());
+    uart.receive(b"34\n");
+    cpu.run(100);
+    assert_eq!(cpu.view_exit_code(), Some(0));
+    assert_eq!(cpu.view_registers()[10], 1234);
+}
+
+#[test]
+fn sbrk_hands_out_memory_after_the_program() {
+    let mut cpu = cpu(SyscallAbi::Rars, "
+        li a0, 16
+        li a7, 9
+        ecall
+        mv s0, a0
+        li a0, 8
+        ecall
+        mv s1, a0
+        li a0, 0x100000
+        ecall
+        mv s2, a0
+        sw s1, 0(s0)
+        ebreak
+    ");
+    cpu.run(100);
+    assert_eq!(cpu.view_fault(), None);
+    // the program is 11 instructions at 0x100, so the heap starts at 0x130 lined up to 8
+    assert_eq!(cpu.view_registers()[8], 0x130);
+    assert_eq!(cpu.view_registers()[9], 0x140);
+    // doesnt fit
+    assert_eq!(cpu.view_registers()[18], u32::MAX);
+    assert_eq!(cpu.view_word(0x130), Some(0x140));
+}
+
+#[test]
+fn what_still_traps() {
+    // numbers that arent in the table
+    let mut cpu = cpu(SyscallAbi::Rars, "li a7, 1000\necall");
+    cpu.run(100);
+    assert_eq!(cpu.view_fault(), Some(&Trap::EnvironmentCall));
+
+    // everything with no abi
+    let mut cpu = self::cpu(SyscallAbi::None, "li a7, 10\necall");
+    cpu.run(100);
+    assert_eq!(cpu.view_fault(), Some(&Trap::EnvironmentCall));
+
+    // a handler gets the ones the abi doesnt know, the rest still get answered like rars does
+    let mut cpu = self::cpu(SyscallAbi::Rars, "
+        la t0, handler
+        csrw mtvec, t0
+        li a7, 1000
+        ecall
+        li a0, 42
+        li a7, 1
+        ecall
+        li a7, 10
+        ecall
+        handler:
+        csrr t0, mepc
+        addi t0, t0, 4
+        csrw mepc, t0
+        addi s0, s0, 1
+        mret
+    ");
+    cpu.run(100);
+    assert_eq!(cpu.view_registers()[8], 1);
+    assert_eq!(cpu.view_uart().unwrap().take_output(), b"42");
+    assert_eq!(cpu.view_exit_code(), Some(0));
+
+    // with no abi the handler gets all of them
+    let mut cpu = self::cpu(SyscallAbi::None, "
+        la t0, handler
+        csrw mtvec, t0
+        li a7, 10
+        ecall
+        li a0, 7
+        csrw mtvec, zero
+        ebreak
+        handler:
+        csrr t0, mepc
+        addi t0, t0, 4
+        csrw mepc, t0
+        mret
+    ");
+    cpu.run(100);
+    assert_eq!(cpu.view_exit_code(), None);
+    assert_eq!(cpu.view_registers()[10], 7);
+}
+
+#[test]
+fn linux_write_read_and_brk() {
+    let mut cpu = cpu(SyscallAbi::Linux, "
+        li a0, 1
+        la a1, message
+        li a2, 6
+        li a7, 64
+        ecall
+        mv s0, a0
+        li a0, 0
+        la a1, buffer
+        li a2, 16
+        li a7, 63
+        ecall
+        mv s1, a0
+        li a0, 0
+        li a7, 214
+        ecall
+        mv s2, a0
+        addi a0, a0, 64
+        ecall
+        mv s3, a0
+        li a7, 1000
+        ecall
+        mv s4, a0
+        la s5, buffer
+        li a0, 5
+        li a7, 93
+        ecall
+        .data
+        message: .string \"hello\\n\"
+        buffer: .word 0, 0, 0, 0
+    ");
+    cpu.view_uart().unwrap().receive(b"abc\ndef\n");
+    cpu.run(100);
+    assert_eq!(cpu.view_exit_code(), Some(5));
+    assert_eq!(output(&cpu), "hello\n");
+    let registers = cpu.view_registers();
+    assert_eq!(registers[8], 6);
+    // one line at a time
+    assert_eq!(registers[9], 4);
+    assert_eq!(registers[19], registers[18] + 64);
+    assert_eq!(registers[20] as i32, -38);
+    assert_eq!(cpu.view_word(registers[21]), Some(u32::from_le_bytes(*b"abc\n")));
+}
+
+#[test]
+fn linux_files_stay_in_the_sandbox() {
+    let sandbox = std::env::temp_dir().join(format!("riscvemulator-sandbox-{}", std::process::id()));
+    fs::create_dir_all(&sandbox).unwrap();
+    fs::write(sandbox.join("in.txt"), "from the host").unwrap();
+
+    let source = "
+        li s11, -100
+        # write out.txt
+        mv a0, s11
+        la a1, out
+        li a2, 0x601          # O_WRONLY | O_CREAT | O_TRUNC
+        li a7, 56
+        ecall
+        mv s0, a0
+        la a1, out
+        li a2, 7
+        li a7, 64
+        ecall
+        mv a0, s0
+        li a7, 57
+        ecall
+        # read in.txt into the buffer
+        mv a0, s11
+        la a1, in
+        li a2, 0
+        li a7, 56
+        ecall
+        mv s1, a0
+        la a1, buffer
+        li a2, 8
+        li a7, 63
+        ecall
+        mv s2, a0
+        # no getting out
+        mv a0, s11
+        la a1, escape
+        li a2, 0
+        li a7, 56
+        ecall
+        mv s3, a0
+        li a7, 93
+        ecall
+        .data
+        out: .string \"out.txt\"
+        in: .string \"in.txt\"
+        escape: .string \"../in.txt\"
+        buffer: .word 0, 0
+    ";
+
+    let mut cpu = cpu(SyscallAbi::Linux, source);
+    cpu.set_sandbox(Some(sandbox.clone()));
+    cpu.run(1000);
+    let registers = cpu.view_registers();
+    assert_eq!(registers[8], 3);
+    // out.txt was closed so in.txt gets the same fd
+    assert_eq!(registers[9], 3);
+    assert_eq!(registers[18], 8);
+    assert_eq!(registers[19] as i32, -13);
+    assert_eq!(fs::read_to_string(sandbox.join("out.txt")).unwrap(), "out.txt");
+
+    // without a sandbox nothing opens
+    let mut cpu = self::cpu(SyscallAbi::Linux, source);
+    cpu.run(1000);
+    assert_eq!(cpu.view_registers()[8] as i32, -13);
+
+    fs::remove_dir_all(&sandbox).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_cant_lead_out_of_the_sandbox() {
+    use std::os::unix::fs::symlink;
+    let dir = std::env::temp_dir().join(format!("riscvemulator-symlinks-{}", std::process::id()));
+    let sandbox = dir.join("sandbox");
+    let outside = dir.join("outside");
+    fs::create_dir_all(&sandbox).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+    fs::write(outside.join("secret.txt"), "secret").unwrap();
+    fs::write(sandbox.join("fine.txt"), "fine").unwrap();
+    symlink(outside.join("secret.txt"), sandbox.join("secret.txt")).unwrap();
+    symlink(&outside, sandbox.join("out")).unwrap();
+    symlink(outside.join("nothing.txt"), sandbox.join("dangling.txt")).unwrap();
+    symlink(sandbox.join("fine.txt"), sandbox.join("also-fine.txt")).unwrap();
+
+    // opens each name in turn and keeps what came back in s0..s4
+    let mut cpu = cpu(SyscallAbi::Linux, "
+        li s11, -100
+        li a7, 56
+        mv a0, s11
+        la a1, secret
+        li a2, 0
+        ecall
+        mv s0, a0
+        mv a0, s11
+        la a1, created
+        li a2, 0x601
+        ecall
+        mv s1, a0
+        mv a0, s11
+        la a1, dangling
+        li a2, 0x601
+        ecall
+        mv s2, a0
+        mv a0, s11
+        la a1, fine
+        li a2, 0
+        ecall
+        mv s3, a0
+        mv a0, s11
+        la a1, new
+        li a2, 0x601
+        ecall
+        mv s4, a0
+        li a7, 93
+        ecall
+        .data
+        secret: .string \"secret.txt\"
+        created: .string \"out/created.txt\"
+        dangling: .string \"dangling.txt\"
+        fine: .string \"also-fine.txt\"
+        new: .string \"new.txt\"
+    ");
+    cpu.set_sandbox(Some(sandbox.clone()));
+    cpu.run(1000);
+    let registers = cpu.view_registers();
+    assert_eq!(registers[8] as i32, -13);
+    assert_eq!(registers[9] as i32, -13);
+    assert!(registers[18] as i32 <= 0);
+    // links that stay inside and new files are still fine
+    assert_eq!(registers[19], 3);
+    assert_eq!(registers[20], 4);
+    assert!(!outside.join("created.txt").exists());
+    assert!(!outside.join("nothing.txt").exists());
+    assert!(sandbox.join("new.txt").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
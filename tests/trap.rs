@@ -0,0 +1,105 @@
+mod common;
+
+use common::run;
+use riscvemulator::{Assembler, Trap, CPU};
+
+// with no handler the cpu stops on the trap, and the csrs say what happened
+fn fault(source: &str) -> (Trap, u32, u32, u32) {
+    let cpu = run(source);
+    assert!(cpu.is_halted());
+    let csrs = cpu.view_csrs();
+    (*cpu.view_fault().unwrap(), csrs.mcause, csrs.mepc, csrs.mtval)
+}
+
+#[test]
+fn misaligned_accesses() {
+    assert_eq!(fault("li t0, 0x181\nlw a0, 0(t0)"), (Trap::LoadAddressMisaligned(0x181), 4, 0x104, 0x181));
+    assert_eq!(fault("li t0, 0x180\nlh a0, 3(t0)"), (Trap::LoadAddressMisaligned(0x183), 4, 0x104, 0x183));
+    assert_eq!(fault("li t0, 0x182\nsw a0, 0(t0)"), (Trap::StoreAddressMisaligned(0x182), 6, 0x104, 0x182));
+    // bytes are never misaligned
+    let cpu = run("li t0, 0x183\nsb t0, 0(t0)\nlbu a0, 0(t0)\nebreak");
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_registers()[10], 0x83);
+    // jumping somewhere that isnt a multiple of 4, mepc is the jump
+    assert_eq!(fault("li t0, 0x102\njalr t0"), (Trap::InstructionAddressMisaligned(0x102), 0, 0x104, 0x102));
+}
+
+#[test]
+fn access_faults() {
+    // nothing is mapped at 0x4000 in the default layout
+    assert_eq!(fault("li t0, 0x4000\nlw a0, 0(t0)"), (Trap::LoadAccessFault(0x4000), 5, 0x104, 0x4000));
+    assert_eq!(fault("li t0, 0x4000\nsb a0, 1(t0)"), (Trap::StoreAccessFault(0x4001), 7, 0x104, 0x4001));
+    // running off somewhere unmapped, mepc is where it tried to fetch
+    assert_eq!(fault("li t0, 0x4000\njr t0"), (Trap::InstructionAccessFault(0x4000), 1, 0x4000, 0x4000));
+}
+
+#[test]
+fn illegal_instructions() {
+    // mtval is the word that couldnt be decoded
+    assert_eq!(fault("nop\n.word 0xFFFFFFFF"), (Trap::IllegalInstruction(0xFFFF_FFFF), 2, 0x104, 0xFFFF_FFFF));
+    // and so is a csr that doesnt exist
+    let csrrs = Assembler::from_source("csrr a0, 0x7FF").assemble().unwrap()[0];
+    assert_eq!(fault("csrr a0, 0x7FF"), (Trap::IllegalInstruction(csrrs), 2, 0x100, csrrs));
+}
+
+#[test]
+fn ecall_and_ebreak() {
+    // an ecall number the syscalls dont know still traps
+    assert_eq!(fault("li a7, 1000\necall"), (Trap::EnvironmentCall, 11, 0x104, 0));
+
+    // ebreak stops it too, but thats how programs end so it isnt a fault
+    let cpu = run("nop\nebreak");
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_csrs().mcause, 3);
+    assert_eq!(cpu.view_csrs().mepc, 0x104);
+    assert_eq!(cpu.get_pc(), 0x104);
+}
+
+#[test]
+fn handlers_and_mret() {
+    let cpu = run("
+        la t0, handler
+        csrw mtvec, t0
+        csrsi mstatus, 8      # mie on
+        li t1, 0x181
+        lw a0, 0(t1)          # skipped by the handler
+        li a1, 1
+        csrr a2, mstatus
+        ebreak
+        handler:
+        csrr s0, mcause
+        csrr s1, mepc
+        csrr s2, mtval
+        csrr s3, mstatus      # mie is off in here and mpie has the old one
+        addi s1, s1, 4
+        csrw mepc, s1
+        csrw mtvec, zero      # so the ebreak stops it
+        mret
+    ");
+    assert_eq!(cpu.view_fault(), None);
+    let registers = cpu.view_registers();
+    assert_eq!(registers[8], 4);
+    assert_eq!(registers[9], 0x118);
+    assert_eq!(registers[18], 0x181);
+    assert_eq!(registers[19] & 0x88, 0x80);
+    // back where it left off with mie turned back on
+    assert_eq!(registers[10], 0);
+    assert_eq!(registers[11], 1);
+    assert_eq!(registers[12] & 0x8, 0x8);
+}
+
+#[test]
+fn traps_program() {
+    let assembler = Assembler::open_file("programs/traps.rv").unwrap();
+    let mut cpu = CPU::default();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+    cpu.run(1000);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_fault(), None);
+    let registers = cpu.view_registers();
+    // the handler ran for the misaligned load and the ecall
+    assert_eq!(registers[6], 4 + 11);
+    // 4 before the load, 6 in the handler each time and the csrrw, the two that trapped dont count
+    assert_eq!(registers[4], 17);
+}
@@ -0,0 +1,104 @@
+use riscvemulator::uart::{UART_BASE, UART_SIZE};
+use riscvemulator::{Assembler, Bus, CpuConfig, Uart, CPU};
+
+fn load(cpu: &mut CPU, file: &str) {
+    let assembler = Assembler::open_file(file).unwrap();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+}
+
+#[test]
+fn hello_world() {
+    let mut cpu = CPU::default();
+    load(&mut cpu, "programs/hello.rv");
+    cpu.run(10_000);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_fault(), None);
+    assert_eq!(cpu.view_uart().unwrap().take_output(), b"hello world\n");
+    // taking it empties it
+    assert_eq!(cpu.view_uart().unwrap().take_output(), b"");
+}
+
+#[test]
+fn echoes_typed_bytes() {
+    let mut cpu = CPU::default();
+    load(&mut cpu, "programs/echo.rv");
+    // nothing typed yet so it just waits
+    cpu.run(1000);
+    assert!(!cpu.is_halted());
+
+    let uart = cpu.view_uart().unwrap().clone();
+    uart.receive(b"hi");
+    cpu.run(1000);
+    assert_eq!(uart.take_output(), b"hi");
+    uart.receive(b"q");
+    cpu.run(1000);
+    assert!(cpu.is_halted());
+    assert_eq!(uart.take_output(), b"q");
+}
+
+#[test]
+fn input_source_only_asked_when_needed() {
+    let mut cpu = CPU::default();
+    load(&mut cpu, "programs/echo.rv");
+    let mut input = b"abq".iter().copied();
+    cpu.view_uart().unwrap().set_input(move || input.next());
+    cpu.run(1000);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_uart().unwrap().take_output(), b"abq");
+
+    // hello world never reads so the source is never asked
+    let mut cpu = CPU::default();
+    load(&mut cpu, "programs/hello.rv");
+    cpu.view_uart().unwrap().set_input(|| panic!("hello world doesnt read input"));
+    cpu.run(10_000);
+    assert!(cpu.is_halted());
+}
+
+#[test]
+fn registers() {
+    let mut uart = Uart::new();
+    // lsr says theres room to send but nothing to read
+    assert_eq!(uart.read_byte(5), Ok(0x60));
+    uart.receive(b"x");
+    assert_eq!(uart.read_byte(5), Ok(0x61));
+    // looking doesnt take it
+    assert_eq!(uart.peek(0), None);
+    assert_eq!(uart.peek(5), Some(0x61));
+    assert_eq!(uart.read_byte(0), Ok(b'x'));
+    assert_eq!(uart.read_byte(5), Ok(0x60));
+
+    // dlab swaps the divisor in and thr stops sending
+    uart.write_byte(3, 0x83).unwrap();
+    uart.write_byte(0, 0x0C).unwrap();
+    uart.write_byte(1, 0x00).unwrap();
+    assert_eq!(uart.read_byte(0), Ok(0x0C));
+    uart.write_byte(3, 0x03).unwrap();
+    assert_eq!(uart.read_byte(3), Ok(0x03));
+    assert_eq!(uart.take_output(), b"");
+
+    // turning on the fifos shows up in iir, and clearing rx drops whats waiting
+    uart.receive(b"abc");
+    uart.write_byte(2, 0x03).unwrap();
+    assert_eq!(uart.read_byte(2), Ok(0xC1));
+    assert_eq!(uart.read_byte(5), Ok(0x60));
+
+    uart.write_byte(7, 0x5A).unwrap();
+    assert_eq!(uart.read_byte(7), Ok(0x5A));
+    uart.reset();
+    assert_eq!(uart.read_byte(7), Ok(0));
+}
+
+#[test]
+fn where_the_uart_goes() {
+    let cpu = CPU::default();
+    assert!(cpu.view_bus().regions().any(|region| region == ("uart", UART_BASE, UART_SIZE)));
+
+    let cpu = CPU::new(CpuConfig::default().uart(None)).unwrap();
+    assert!(cpu.view_uart().is_none());
+    assert_eq!(cpu.view_bus().regions().count(), 1);
+
+    // cant be on top of ram
+    assert!(CpuConfig::default().uart(Some(0x100)).check().is_err());
+    assert!(CpuConfig::default().uart(Some(0xFFFF_FFFC)).check().is_err());
+    assert!(CpuConfig::default().uart(Some(0x200)).check().is_ok());
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::load;
+use riscvemulator::{Assembler, CpuConfig, SyscallAbi, WatchHit, WatchKind, Watchpoint, CPU};
+
+const STORES: &str = "
+    li t0, 0x180
+    li t1, 5
+    sw t1, 0(t0)
+    sw t1, 0(t0)
+    lw t2, 0(t0)
+    li t1, 6
+    sb t1, 1(t0)
+    li a0, 1
+    ebreak
+";
+
+#[test]
+fn writes_stop_after_the_store() {
+    let (_, mut cpu) = load(STORES);
+    let watchpoint = Watchpoint::new(0x180, 4, WatchKind::Write);
+    cpu.add_watchpoint(watchpoint);
+
+    assert_eq!(cpu.run(u64::MAX), 3);
+    assert_eq!(cpu.view_watch_hit(), Some(&WatchHit { watchpoint, pc: 0x108, address: 0x180, size: 4, old: 0, new: 5 }));
+    // the store still happened
+    assert_eq!(cpu.view_word(0x180), Some(5));
+
+    // the same value again is still a write
+    assert_eq!(cpu.run(u64::MAX), 1);
+    assert_eq!(cpu.view_watch_hit().map(|hit| (hit.pc, hit.old, hit.new)), Some((0x10C, 5, 5)));
+
+    // the load doesnt count, the sb does
+    assert_eq!(cpu.run(u64::MAX), 3);
+    assert_eq!(cpu.view_watch_hit().map(|hit| (hit.pc, hit.address, hit.size, hit.new)), Some((0x118, 0x181, 1, 6)));
+
+    cpu.remove_watchpoint(&watchpoint);
+    assert!(cpu.view_watchpoints().is_empty());
+    cpu.run(u64::MAX);
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.view_watch_hit(), None);
+}
+
+#[test]
+fn changes_only_count_the_watched_bytes() {
+    let (_, mut cpu) = load(STORES);
+    // the second sw leaves it alone
+    cpu.add_watchpoint(Watchpoint::new(0x180, 1, WatchKind::Change));
+    cpu.run(u64::MAX);
+    assert_eq!(cpu.view_watch_hit().map(|hit| hit.pc), Some(0x108));
+    cpu.run(u64::MAX);
+    assert!(cpu.is_halted());
+
+    // the sw only changes the first byte, the sb changes this one
+    let (_, mut cpu) = load(STORES);
+    cpu.add_watchpoint(Watchpoint::new(0x181, 1, WatchKind::Change));
+    cpu.run(u64::MAX);
+    assert_eq!(cpu.view_watch_hit().map(|hit| (hit.pc, hit.old, hit.new)), Some((0x118, 0, 6)));
+}
+
+#[test]
+fn reads_and_resets() {
+    let (_, mut cpu) = load(STORES);
+    cpu.add_watchpoint(Watchpoint::new(0x183, 1, WatchKind::Read));
+    assert_eq!(cpu.run(u64::MAX), 5);
+    assert_eq!(cpu.view_watch_hit().map(|hit| (hit.pc, hit.address, hit.old, hit.new)), Some((0x110, 0x180, 5, 5)));
+    assert_eq!(cpu.view_registers()[7], 5);
+
+    // stepping on clears it, and watchpoints stay through a reset
+    cpu.step();
+    assert_eq!(cpu.view_watch_hit(), None);
+    cpu.reset();
+    cpu.run(u64::MAX);
+    assert_eq!(cpu.view_watch_hit().map(|hit| hit.pc), Some(0x110));
+    assert_eq!(cpu.view_watchpoints().len(), 1);
+}
+
+#[test]
+fn covered_bytes() {
+    let watchpoint = Watchpoint::new(0x100, 4, WatchKind::Write);
+    assert!(watchpoint.covers(0x100, 1));
+    assert!(watchpoint.covers(0xFE, 4));
+    assert!(watchpoint.covers(0x103, 2));
+    assert!(!watchpoint.covers(0xFC, 4));
+    assert!(!watchpoint.covers(0x104, 1));
+    // right at the top of the address space
+    assert!(Watchpoint::new(0xFFFF_FFFC, 4, WatchKind::Read).covers(0xFFFF_FFFF, 1));
+    assert_eq!(WatchKind::from_name("change"), Some(WatchKind::Change));
+    assert_eq!(WatchKind::from_name("exec"), None);
+}
+
+#[test]
+fn ecalls_writing_memory() {
+    let config = CpuConfig::preset("small").unwrap().syscalls(SyscallAbi::Linux);
+    let mut assembler = Assembler::from_source("
+        li a0, 0
+        la a1, buffer
+        li a2, 8
+        li a7, 63
+        ecall
+        li a7, 93
+        ecall
+        .data
+        buffer: .word 0, 0
+    ");
+    assembler.set_base(config.reset_vector);
+    let buffer = assembler.view_labels().into_iter().find(|(label, _)| label == "buffer").unwrap().1;
+    let mut cpu = CPU::new(config).unwrap();
+    cpu.load_program(&assembler.assemble_bytes().unwrap()).unwrap();
+    cpu.view_uart().unwrap().receive(b"abcd\n");
+    let watchpoint = Watchpoint::new(buffer + 2, 2, WatchKind::Change);
+    cpu.add_watchpoint(watchpoint);
+
+    cpu.run(100);
+    // the read into the buffer, stopped on the first watched byte it wrote
+    let ecall = 0x100 + 5 * 4;
+    assert_eq!(cpu.view_watch_hit(), Some(&WatchHit { watchpoint, pc: ecall, address: buffer + 2, size: 1, old: 0, new: b'c' as u32 }));
+    assert_eq!(cpu.view_word(buffer), Some(u32::from_le_bytes(*b"abcd")));
+
+    // and it can be undone like a store
+    cpu.step_back();
+    assert_eq!(cpu.view_word(buffer), Some(0));
+    assert_eq!(cpu.get_pc(), ecall);
+}